@@ -0,0 +1,42 @@
+use tracing::debug;
+
+use crate::{
+    Result,
+    audit::{self, AuditQuery},
+    output::{self, message},
+};
+
+/// Prints the records of the audit log matching the query, oldest first
+pub fn audit(query: AuditQuery) -> Result<()> {
+    debug!("Received the following parameters: query: [{query:?}]");
+
+    let records = audit::read(&query)?;
+    if output::is_json() {
+        output::record_audit(records);
+        return Ok(());
+    }
+
+    if records.is_empty() {
+        message!("No matching records in the audit log");
+    }
+    for record in records {
+        let outcome = if record.success {
+            String::from("succeeded")
+        } else {
+            format!("failed with exit code {:?}", record.exit_code)
+        };
+        message!(
+            "{:.0} {} {}/{} by {}@{} on {}: {outcome} in {}s",
+            record.timestamp,
+            record.action,
+            record.namespace,
+            record.release,
+            record.user,
+            record.host,
+            record.context.as_deref().unwrap_or("unknown context"),
+            record.duration_ms / 1000,
+        );
+    }
+
+    Ok(())
+}
@@ -1,33 +1,352 @@
-use std::path::PathBuf;
+use std::{
+    num::NonZero,
+    panic,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
 use tracing::{debug, info};
 
-use super::values;
-use crate::{Result, command::Command, deploy_config::*};
+use super::{lint_values, template, values};
+use crate::{
+    Error, Result,
+    command::Command,
+    deploy_config::*,
+    output::{self, CheckReport, ReleaseAction, message},
+    policy::{self, Severity},
+    settings::{self, deployment_file_name},
+};
 
-pub fn check(profile: Option<String>, deploy_file_dir: PathBuf) -> Result<()> {
+/// Settings of a check
+#[derive(Debug, Default)]
+pub struct CheckOptions {
+    /// Checks the rendered manifests against the policies of the settings
+    pub policies: bool,
+    /// Checks every deployment below the directory with every profile found, at the same time
+    pub recursive: bool,
+    /// How many directory levels below the directory are searched by a recursive check
+    pub depth: usize,
+    /// Renders the chart with helm template instead of a dry-run, which needs no cluster
+    pub offline: bool,
+}
+
+/// Checks every release defined in the directory
+pub fn check(
+    profile: Option<String>,
+    deploy_file_dir: PathBuf,
+    file: Option<String>,
+    options: CheckOptions,
+) -> Result<()> {
     debug!(
-        "Received the following parameters: profile: [{:?}], dir: [{:?}]",
-        profile, deploy_file_dir
+        "Received the following parameters: profile: [{:?}], dir: [{:?}], file: [{:?}], options: [{:?}]",
+        profile, deploy_file_dir, file, options
     );
 
-    let deployment = Deployment::new(&deploy_file_dir, None)?;
+    let file_name = deployment_file_name(file);
+    if options.recursive {
+        return check_recursive(profile, &deploy_file_dir, &file_name, &options);
+    }
+
+    let deployments = Deployment::all_in(&deploy_file_dir, &file_name)?;
     info!("Deployment file found. Checking deployment");
 
-    let values_default = values::default(deploy_file_dir.clone())?;
-    let values_profile = values::profile(deploy_file_dir, &profile)?;
+    for deployment in deployments {
+        check_release(&deployment, &profile, &options, true)?;
+    }
+
+    Ok(())
+}
+
+/// Checks every release below the directory at the same time and prints a summary.
+/// Without a profile, every profile with a values file is checked
+fn check_recursive(
+    profile: Option<String>,
+    dir: &Path,
+    file_name: &DeploymentFileName,
+    options: &CheckOptions,
+) -> Result<()> {
+    let (deployments, invalid) = if file_name.exists_in(dir) {
+        match Deployment::all_in(dir, file_name) {
+            Ok(deployments) => (deployments, Vec::new()),
+            Err(err) => (Vec::new(), vec![(dir.to_path_buf(), err)]),
+        }
+    } else {
+        Deployment::discover_all(dir, file_name, options.depth)?
+    };
+
+    let mut checks = Vec::new();
+    for deployment in &deployments {
+        let profiles = match &profile {
+            Some(profile) => vec![Some(profile.clone())],
+            None => {
+                let found = values::profiles(deployment.dir(), deployment.variant())?;
+                if found.is_empty() {
+                    vec![None]
+                } else {
+                    found.into_iter().map(Some).collect()
+                }
+            }
+        };
+        checks.extend(profiles.into_iter().map(|profile| (deployment, profile)));
+    }
+    message!(
+        "Checking {} releases in {} checks",
+        deployments.len(),
+        checks.len()
+    );
+
+    let results = run_concurrently(&checks, |(deployment, profile)| {
+        check_release(deployment, profile, options, false)
+    });
+
+    let mut reports: Vec<CheckReport> = invalid
+        .into_iter()
+        .map(|(file, err)| CheckReport {
+            release: file.display().to_string(),
+            profile: None,
+            passed: false,
+            error: Some(err.to_string()),
+        })
+        .collect();
+    reports.extend(
+        checks
+            .iter()
+            .zip(results)
+            .map(|((deployment, profile), result)| CheckReport {
+                release: deployment.release_name().to_string(),
+                profile: profile.clone(),
+                passed: result.is_ok(),
+                error: result.err().map(|err| err.to_string()),
+            }),
+    );
+
+    for report in &reports {
+        let Some(error) = &report.error else {
+            continue;
+        };
+        match &report.profile {
+            Some(profile) => message!("{} ({profile}) failed:\n{error}\n", report.release),
+            None => message!("{} failed:\n{error}\n", report.release),
+        }
+    }
+    message!("{}", summary(&reports));
+
+    let failed = reports.iter().filter(|report| !report.passed).count();
+    let total = reports.len();
+    output::record_checks(reports);
+    if failed > 0 {
+        return Err(Error::ChecksFailed(failed, total));
+    }
+
+    Ok(())
+}
+
+/// Lints the values files, runs the dry-run, or renders the chart offline,
+/// and if enabled the policy checks of the release.
+/// Live checks print everything while it runs. A failed dry-run of them is only reported by helm,
+/// other checks fail with it
+fn check_release(
+    deployment: &Deployment,
+    profile: &Option<String>,
+    options: &CheckOptions,
+    live: bool,
+) -> Result<()> {
+    let dir = deployment.dir().to_path_buf();
+    let values_default = values::default(dir.clone(), deployment.variant())?;
+    let values_profile = values::profile(dir, profile, deployment.variant())?;
+    lint_values::lint_release(deployment, &values_default, values_profile.as_slice(), live)?;
+    let values_profile = values_profile.as_ref().map(|p| p.path());
+
+    let manifest = if options.offline {
+        Some(render_offline(
+            deployment,
+            values_default.path(),
+            values_profile,
+            live,
+        )?)
+    } else {
+        dry_run(deployment, values_default.path(), values_profile, live)?;
+        None
+    };
+
+    if options.policies {
+        let manifest = match manifest {
+            Some(manifest) => manifest,
+            None => template::render(deployment, values_default.path(), values_profile)?,
+        };
+        check_policies(deployment, &manifest, live)?;
+    }
+
+    Ok(())
+}
+
+fn dry_run(
+    deployment: &Deployment,
+    values_default: &Path,
+    values_profile: Option<&Path>,
+    live: bool,
+) -> Result<()> {
+    let name = deployment.release_name();
+    let command = create_check(deployment, values_default, values_profile);
+    let execution = if live {
+        command.execute()?
+    } else {
+        command.execute_captured()?
+    };
+
+    let failure = (execution.exit_code != Some(0))
+        .then(|| Error::CheckFailed(name.to_string(), execution.stderr.trim().to_string()));
+    output::record_executed(
+        name,
+        &deployment.chart.namespace,
+        ReleaseAction::Check,
+        execution,
+    );
+    match failure.filter(|_| !live) {
+        Some(failure) => Err(failure),
+        None => Ok(()),
+    }
+}
+
+/// Renders the chart with helm template, which needs no cluster. Returns the rendered manifest
+fn render_offline(
+    deployment: &Deployment,
+    values_default: &Path,
+    values_profile: Option<&Path>,
+    live: bool,
+) -> Result<String> {
+    let name = deployment.release_name();
+    let mut execution =
+        template::create_template(deployment, values_default, values_profile).execute_captured()?;
 
-    create_check(deployment, values_default, values_profile).execute()?;
+    let manifest = std::mem::take(&mut execution.stdout);
+    let failure = (execution.exit_code != Some(0))
+        .then(|| Error::CheckFailed(name.to_string(), execution.stderr.trim().to_string()));
+    output::record_executed(
+        name,
+        &deployment.chart.namespace,
+        ReleaseAction::Check,
+        execution,
+    );
+    if let Some(failure) = failure {
+        return Err(failure);
+    }
+    if live {
+        message!("{name} renders without errors");
+    }
+
+    Ok(manifest)
+}
+
+/// Runs every job on as many threads as there are CPUs. Results are in the order of the jobs
+fn run_concurrently<T: Sync, R: Send>(jobs: &[T], run: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let workers = thread::available_parallelism()
+        .map_or(1, NonZero::get)
+        .min(jobs.len());
+    let next = AtomicUsize::new(0);
+
+    let mut results: Vec<(usize, R)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(job) = jobs.get(index) else {
+                            return results;
+                        };
+                        results.push((index, run(job)));
+                    }
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+    results.sort_by_key(|(index, _)| *index);
+
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Table of the result of every check
+fn summary(reports: &[CheckReport]) -> String {
+    let rows: Vec<[&str; 3]> = reports
+        .iter()
+        .map(|report| {
+            [
+                report.release.as_str(),
+                report.profile.as_deref().unwrap_or("-"),
+                if report.passed { "passed" } else { "failed" },
+            ]
+        })
+        .collect();
+    let header = ["RELEASE", "PROFILE", "RESULT"];
+    let width = |column: usize| {
+        rows.iter()
+            .chain([&header])
+            .map(|row| row[column].len())
+            .max()
+            .unwrap_or_default()
+    };
+    let (release, profile) = (width(0), width(1));
+
+    [header]
+        .iter()
+        .chain(&rows)
+        .map(|row| format!("{:<release$}  {:<profile$}  {}", row[0], row[1], row[2]))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Reports every violation. Fails if any of them is an error.
+/// Violations are only printed by live checks
+fn check_policies(deployment: &Deployment, manifest: &str, live: bool) -> Result<()> {
+    let name = deployment.release_name();
+    let namespace = &deployment.chart.namespace;
+    let violations = policy::evaluate(
+        manifest,
+        namespace,
+        &settings::get().policies,
+        deployment.chart.suppressed_policies(),
+    )?;
+
+    if live {
+        for violation in &violations {
+            message!("{violation}");
+        }
+    }
+    let errors: Vec<String> = violations
+        .iter()
+        .filter(|violation| violation.severity == Severity::Error)
+        .map(ToString::to_string)
+        .collect();
+    output::record_policies(name, namespace, violations);
+
+    if !errors.is_empty() {
+        return Err(Error::PolicyViolations(name.to_string(), errors));
+    }
+    if live {
+        message!("{name} complies with the policies");
+    }
 
     Ok(())
 }
 
 fn create_check(
-    deployment: Deployment,
-    values_default: PathBuf,
-    values_profile: Option<PathBuf>,
+    deployment: &Deployment,
+    values_default: &Path,
+    values_profile: Option<&Path>,
 ) -> Command {
-    let mut command = Command::new("helm");
+    let mut command = Command::helm();
     command
+        .release(deployment.release_name())
         .args(["upgrade", "--install"])
         .arg("--dry-run")
         .args(["-f", values_default.to_str().unwrap()]);
@@ -42,24 +361,67 @@ fn create_check(
 
 #[cfg(test)]
 mod tests {
-    use std::{error::Error, path::PathBuf, str::FromStr};
+    use std::{error::Error, path::Path};
 
     use crate::deploy_config::test_fixtures::deployment;
 
-    use super::create_check;
+    use super::{create_check, run_concurrently, summary};
+    use crate::output::CheckReport;
 
     type TestResult = std::result::Result<(), Box<dyn Error>>;
 
+    #[test]
+    fn results_in_order_of_jobs() {
+        // given
+        let jobs: Vec<u64> = (0..20).collect();
+
+        // when
+        let result = run_concurrently(&jobs, |job| job * 2);
+
+        // then
+        assert_eq!(result, jobs.iter().map(|job| job * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn summary_aligned_per_release_and_profile() {
+        // given
+        let reports = [
+            CheckReport {
+                release: String::from("nginx"),
+                profile: Some(String::from("prod")),
+                passed: true,
+                error: None,
+            },
+            CheckReport {
+                release: String::from("shop-api"),
+                profile: None,
+                passed: false,
+                error: Some(String::from("values-default.yaml missing")),
+            },
+        ];
+
+        // when
+        let result = summary(&reports);
+
+        // then
+        assert_eq!(
+            result,
+            "RELEASE   PROFILE  RESULT\n\
+             nginx     prod     passed\n\
+             shop-api  -        failed"
+        );
+    }
+
     #[rustfmt::skip]
     #[test]
     fn check_no_profile() -> TestResult {
         // given
         let deployment = deployment();
 
-        let values_default = PathBuf::from_str("values-default.yaml")?;
+        let values_default = Path::new("values-default.yaml");
 
         // when
-        let result = create_check(deployment, values_default, None);
+        let result = create_check(&deployment, values_default, None);
 
         // then
         assert_eq!(result.get_program(), "helm");
@@ -83,11 +445,11 @@ mod tests {
         // given
         let deployment = deployment();
 
-        let values_default = PathBuf::from_str("values-default.yaml")?;
-        let values_profile = PathBuf::from_str("values-test.yaml")?;
+        let values_default = Path::new("values-default.yaml");
+        let values_profile = Path::new("values-test.yaml");
 
         // when
-        let result = create_check(deployment, values_default, Some(values_profile));
+        let result = create_check(&deployment, values_default, Some(values_profile));
 
         // then
         assert_eq!(result.get_program(), "helm");
@@ -1,80 +1,117 @@
-use tracing::{debug, info, warn};
+use tracing::{debug, info};
 
 use crate::Result;
 use crate::actions::cleanup::model::Releases;
 use crate::command::Command;
-use crate::deploy_config::Deployment;
+use crate::deploy_config::{Deployment, DeploymentFileName, Discovery};
 use crate::io::user_confirmation;
+use crate::output::message;
+use crate::settings::{self, deployment_file_name};
 use std::path::PathBuf;
-
-pub fn cleanup_all_namespaces(dir: PathBuf, no_verify: bool) -> Result<()> {
-    debug!("Received the following parameters: all-namespace: [true], dir: [{dir:?}]");
+use std::time::Duration;
+
+/// Returns the amount of releases that were undeployed, or would be undeployed on a dry-run
+pub fn cleanup_all_namespaces(
+    dir: PathBuf,
+    file: Option<String>,
+    discovery: Discovery,
+    no_verify: bool,
+    dry_run: bool,
+    wait_for_lock: Option<Duration>,
+) -> Result<usize> {
+    debug!(
+        "Received the following parameters: all-namespace: [true], dir: [{dir:?}], file: [{file:?}], discovery: [{discovery:?}], dry-run: [{dry_run:?}], wait-for-lock: [{wait_for_lock:?}]"
+    );
     let currently_released = find_currently_released_in_all_namespace()?;
-    let defined_releases = find_defined_releases(dir)?;
-
-    let difference = currently_released.difference(&defined_releases);
-    if difference.len() == 0 {
-        println!("Nothing to cleanup");
-        return Ok(());
-    }
 
-    let user_confirm = format!("The following would be undeployed: {difference}: Proceed? [Y/N]");
-    if !no_verify && !user_confirmation(&user_confirm)? {
-        return Ok(());
-    }
-    difference.undeploy()?;
-
-    Ok(())
+    cleanup(
+        currently_released,
+        dir,
+        file,
+        discovery,
+        no_verify,
+        dry_run,
+        wait_for_lock,
+    )
 }
 
-pub fn cleanup_namespace(namespace: String, dir: PathBuf, no_verify: bool) -> Result<()> {
+/// Returns the amount of releases that were undeployed, or would be undeployed on a dry-run
+pub fn cleanup_namespace(
+    namespace: String,
+    dir: PathBuf,
+    file: Option<String>,
+    discovery: Discovery,
+    no_verify: bool,
+    dry_run: bool,
+    wait_for_lock: Option<Duration>,
+) -> Result<usize> {
     debug!(
-        "Received the following parameters: all-namespaces: [false] namespace: [{namespace:?}], dir: [{dir:?}]"
+        "Received the following parameters: all-namespaces: [false] namespace: [{namespace:?}], dir: [{dir:?}], file: [{file:?}], discovery: [{discovery:?}], dry-run: [{dry_run:?}], wait-for-lock: [{wait_for_lock:?}]"
     );
     let currently_released = find_currently_released_in_namespace(&namespace)?;
-    let defined_releases = find_defined_releases(dir)?;
 
-    let difference = currently_released.difference(&defined_releases);
+    cleanup(
+        currently_released,
+        dir,
+        file,
+        discovery,
+        no_verify,
+        dry_run,
+        wait_for_lock,
+    )
+}
+
+fn cleanup(
+    currently_released: Releases,
+    dir: PathBuf,
+    file: Option<String>,
+    discovery: Discovery,
+    no_verify: bool,
+    dry_run: bool,
+    wait_for_lock: Option<Duration>,
+) -> Result<usize> {
+    let file_name = deployment_file_name(file);
+    let defined_releases = find_defined_releases(dir, &file_name, &discovery)?;
+
+    let mut difference = currently_released.difference(&defined_releases);
+    let protected = difference.split_off_namespaces(&settings::get().protected_namespaces);
+    if protected.len() > 0 {
+        message!("Skipping releases in protected namespaces: {protected}");
+    }
     if difference.len() == 0 {
-        println!("Nothing to cleanup");
-        return Ok(());
+        message!("Nothing to cleanup");
+        return Ok(0);
+    }
+
+    if dry_run {
+        message!("The following would be undeployed: {difference}");
+        difference.dry_run();
+        return Ok(difference.len());
     }
 
     let user_confirm = format!("The following would be undeployed: {difference}: Proceed? [Y/N]");
     if !no_verify && !user_confirmation(&user_confirm)? {
-        return Ok(());
+        return Ok(0);
     }
-    difference.undeploy()?;
 
-    Ok(())
+    let undeployed = difference.len();
+    difference.undeploy(wait_for_lock)?;
+
+    Ok(undeployed)
 }
 
-fn find_defined_releases(dir: PathBuf) -> Result<Releases> {
-    let releases = std::fs::read_dir(dir)?;
-    let releases: Vec<_> = releases
-        .filter_map(|entry| {
-            let entry = entry.ok()?;
-            if entry.file_type().ok()?.is_dir() {
-                let name = entry.file_name().into_string().ok()?;
-                if name.starts_with(".") {
-                    return None;
-                }
-
-                let deployment = match Deployment::new(&entry.path(), None) {
-                    Ok(v) => Some(v),
-                    Err(err) => {
-                        warn!(
-                            "Error when reading deployment.yaml: release: {}, err: {}",
-                            name, err
-                        );
-                        None
-                    }
-                }?;
-
-                return Some((name, deployment.chart.namespace));
-            }
-
-            None
+fn find_defined_releases(
+    dir: PathBuf,
+    file_name: &DeploymentFileName,
+    discovery: &Discovery,
+) -> Result<Releases> {
+    let releases = Deployment::discover(&dir, file_name, discovery)?
+        .into_iter()
+        .map(|deployment| {
+            (
+                deployment.release_name().to_string(),
+                deployment.chart.namespace,
+            )
         })
         .collect();
 
@@ -83,6 +120,24 @@ fn find_defined_releases(dir: PathBuf) -> Result<Releases> {
     releases
 }
 
+/// Checks whether the given release is currently deployed in the namespace
+pub(crate) fn is_released(name: &str, namespace: &str) -> Result<bool> {
+    let releases = find_currently_released_in_namespace(namespace)?;
+
+    Ok(releases.contains(name, namespace))
+}
+
+/// Lists the releases currently deployed in the namespace, or in all namespaces if none is given.
+/// Returns the name and namespace of each release
+pub(crate) fn list_released(namespace: Option<&str>) -> Result<Vec<(String, String)>> {
+    let releases = match namespace {
+        Some(namespace) => find_currently_released_in_namespace(namespace)?,
+        None => find_currently_released_in_all_namespace()?,
+    };
+
+    Ok(releases.into_vec())
+}
+
 fn find_currently_released_in_namespace(namespace: &str) -> Result<Releases> {
     let releases = create_list_releases_in_namespace(namespace).output()?;
 
@@ -90,7 +145,7 @@ fn find_currently_released_in_namespace(namespace: &str) -> Result<Releases> {
 }
 
 fn create_list_releases_in_namespace(namespace: &str) -> Command {
-    let mut command = Command::new("helm");
+    let mut command = Command::helm();
     command
         .arg("list")
         .args(["--namespace", namespace])
@@ -108,7 +163,7 @@ fn find_currently_released_in_all_namespace() -> Result<Releases> {
 }
 
 fn create_list_releases_in_all_namespace() -> Command {
-    let mut command = Command::new("helm");
+    let mut command = Command::helm();
     command.arg("list").arg("-A").args(["-o", "yaml"]);
 
     command
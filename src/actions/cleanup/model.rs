@@ -1,8 +1,12 @@
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
+use std::time::Duration;
 
 use crate::Error;
+use crate::actions::lock;
+use crate::audit::{self, Audited};
 use crate::command::Command;
+use crate::output::{self, ReleaseAction, message};
 use yaml_rust2::{Yaml, YamlLoader};
 
 #[derive(Default, Debug)]
@@ -30,17 +34,58 @@ impl Releases {
         }
     }
 
+    pub(super) fn contains(&self, name: &str, namespace: &str) -> bool {
+        self.content
+            .iter()
+            .any(|(n, ns)| n == name && ns == namespace)
+    }
+
+    /// Removes the releases in any of the namespaces and returns them
+    pub(super) fn split_off_namespaces(&mut self, namespaces: &[String]) -> Self {
+        let (split_off, kept) = std::mem::take(&mut self.content)
+            .into_iter()
+            .partition(|(_, ns)| namespaces.contains(ns));
+        self.content = kept;
+
+        Self { content: split_off }
+    }
+
+    pub(super) fn into_vec(self) -> Vec<Release> {
+        self.content
+    }
+
     pub(super) fn len(&self) -> usize {
         self.content.len()
     }
 
-    pub(super) fn undeploy(self) -> Result<(), Error> {
+    /// Locks every release while it is undeployed
+    pub(super) fn undeploy(self, wait_for_lock: Option<Duration>) -> Result<(), Error> {
         for release in &self.content {
-            println!("Undeploying {} in namespace {}", release.0, release.1);
-            create_undeploy(release).execute()?;
+            let _lock = lock::acquire(&release.0, &release.1, wait_for_lock)?;
+            message!("Undeploying {} in namespace {}", release.0, release.1);
+            let execution = create_undeploy(release).execute()?;
+            audit::record(
+                &Audited::new(ReleaseAction::Undeploy, &release.0, &release.1),
+                &execution,
+            );
+            output::record_executed(&release.0, &release.1, ReleaseAction::Undeploy, execution);
         }
         Ok(())
     }
+
+    /// Prints the helm commands that would be run by undeploy
+    pub(super) fn dry_run(&self) {
+        for release in &self.content {
+            let command = create_undeploy(release);
+            message!("{command}");
+            output::record_planned(
+                &release.0,
+                &release.1,
+                ReleaseAction::PlannedUndeploy,
+                &command,
+            );
+        }
+    }
 }
 
 impl Display for Releases {
@@ -77,8 +122,8 @@ impl FromStr for Releases {
 }
 
 fn create_undeploy(release: &Release) -> Command {
-    let mut command = Command::new("helm");
-    command.args(["uninstall", &release.0]);
+    let mut command = Command::helm();
+    command.release(&release.0).args(["uninstall", &release.0]);
     command.args(["--namespace", &release.1]);
     command
 }
@@ -115,7 +160,7 @@ fn find_release_info(release: &Yaml) -> Option<(Name, Namespace)> {
 #[cfg(test)]
 mod tests {
     use crate::Error;
-    use crate::actions::cleanup::model::Releases;
+    use crate::actions::cleanup::model::{Releases, create_undeploy};
 
     type TestResult = Result<(), Box<dyn std::error::Error>>;
 
@@ -137,6 +182,56 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn contains_only_matching_namespace() -> TestResult {
+        // given
+        let releases: Releases = helper::create_yaml().parse()?;
+
+        // when
+        let matching = releases.contains("test1", "namespace1");
+        let other_namespace = releases.contains("test1", "namespace2");
+
+        // then
+        assert!(matching);
+        assert!(!other_namespace);
+
+        Ok(())
+    }
+
+    #[test]
+    fn split_off_protected_namespaces() -> TestResult {
+        // given
+        let mut releases: Releases = helper::create_yaml().parse()?;
+
+        // when
+        let protected = releases
+            .split_off_namespaces(&[String::from("namespace1"), String::from("namespace3")]);
+
+        // then
+        assert_eq!(protected.len(), 2);
+        assert_eq!(
+            releases.content,
+            [("test2".to_string(), "namespace2".to_string())]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn undeploy_command_per_release() {
+        // given
+        let release = ("test1".to_string(), "namespace1".to_string());
+
+        // when
+        let result = create_undeploy(&release);
+
+        // then
+        assert_eq!(
+            result.to_string(),
+            "helm uninstall test1 --namespace namespace1"
+        );
+    }
+
     #[test]
     fn parse_empty_yaml_ok() -> TestResult {
         // given
@@ -1,21 +1,64 @@
-use crate::{Result, command::Command};
+use crate::{
+    Result,
+    command::Command,
+    output::{self, ClusterReport},
+};
 
 pub fn list_clusters() -> Result<()> {
+    if output::is_json() {
+        let names = create_list_cluster_names().output()?;
+        // Fails if no cluster is currently set
+        let current = create_current_cluster().output().unwrap_or_default();
+        output::record_clusters(parse_clusters(&names, current.trim()));
+
+        return Ok(());
+    }
+
     create_list_clusters().execute()?;
 
     Ok(())
 }
 
 fn create_list_clusters() -> Command {
-    let mut command = Command::new("kubectl");
+    let mut command = Command::kubectl();
     command.arg("config").arg("get-contexts");
 
     command
 }
 
+fn create_list_cluster_names() -> Command {
+    let mut command = Command::kubectl();
+    command
+        .arg("config")
+        .arg("get-contexts")
+        .args(["-o", "name"]);
+
+    command
+}
+
+fn create_current_cluster() -> Command {
+    let mut command = Command::kubectl();
+    command.arg("config").arg("current-context");
+
+    command
+}
+
+fn parse_clusters(names: &str, current: &str) -> Vec<ClusterReport> {
+    names
+        .lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| ClusterReport {
+            name: name.to_string(),
+            current: name == current,
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
-    use super::create_list_clusters;
+    use super::{create_list_cluster_names, create_list_clusters, parse_clusters};
+    use crate::output::ClusterReport;
 
     type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
 
@@ -32,4 +75,47 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn correct_kubectl_names_command() -> TestResult {
+        // given
+
+        // when
+        let command = create_list_cluster_names();
+
+        // then
+        assert_eq!(command.get_program(), "kubectl");
+        assert_eq!(
+            command.get_args(),
+            &["config", "get-contexts", "-o", "name"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_marks_current_cluster() -> TestResult {
+        // given
+        let names = "kind-dev\nprod\n\n";
+
+        // when
+        let result = parse_clusters(names, "prod");
+
+        // then
+        assert_eq!(
+            result,
+            [
+                ClusterReport {
+                    name: String::from("kind-dev"),
+                    current: false
+                },
+                ClusterReport {
+                    name: String::from("prod"),
+                    current: true
+                },
+            ]
+        );
+
+        Ok(())
+    }
 }
@@ -10,7 +10,7 @@ pub fn set_cluster(name: &str) -> Result<()> {
 }
 
 fn create_set_cluster(name: &str) -> Command {
-    let mut command = Command::new("kubectl");
+    let mut command = Command::kubectl();
     command.arg("config").arg("use-context").arg(name);
 
     command
@@ -0,0 +1,30 @@
+use crate::{
+    Result,
+    output::{self, ConfigReport, message},
+    settings,
+};
+
+/// Prints the effective settings and where each of them came from
+pub fn show_config() -> Result<()> {
+    let entries = settings::get().entries();
+
+    if output::is_json() {
+        output::record_config(
+            entries
+                .into_iter()
+                .map(|(key, value, source)| ConfigReport {
+                    key,
+                    value,
+                    source: source.to_string(),
+                })
+                .collect(),
+        );
+        return Ok(());
+    }
+
+    for (key, value, source) in entries {
+        message!("{key} = {value}  # {source}");
+    }
+
+    Ok(())
+}
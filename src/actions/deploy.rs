@@ -1,45 +1,204 @@
-use std::path::PathBuf;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 use tracing::{debug, info};
 
-use super::values;
-use crate::{Result, command::Command, deploy_config::*, io::user_confirmation};
+use super::{
+    health,
+    hooks::{self, HookContext},
+    lock, rollback, test, unlock, values,
+};
+use crate::{
+    Error, Result,
+    audit::{self, Audited},
+    command::Command,
+    deploy_config::*,
+    io::user_confirmation,
+    output::{self, ReleaseAction, message},
+    settings::{self, deployment_file_name},
+};
 
-pub fn deploy(profile: Option<String>, deploy_file_dir: PathBuf, no_verify: bool) -> Result<()> {
+/// Settings of a deploy
+#[derive(Debug, Default)]
+pub struct DeployOptions {
+    /// Deploys without asking for confirmation
+    pub no_verify: bool,
+    /// Verifies the workloads after the deploy. Pods are watched for restarts for the given time
+    pub health_check: Option<Duration>,
+    /// Runs the tests of the chart after the deploy, even if the deployment file does not enable them
+    pub run_tests: bool,
+    /// Restores the last successful revision if the deploy, its health check or its tests fail,
+    /// even if the deployment file does not enable it
+    pub auto_rollback: bool,
+    /// How long to wait for the lock of a release that is changed by someone else
+    pub wait_for_lock: Option<Duration>,
+    /// Recovers releases stuck in a pending state before deploying them
+    pub unlock_pending: bool,
+}
+
+/// Deploys every release defined in the directory
+pub fn deploy(
+    profile: Option<String>,
+    deploy_file_dir: PathBuf,
+    file: Option<String>,
+    options: DeployOptions,
+) -> Result<()> {
     debug!(
-        "Received the following parameters: profile: [{:?}], dir: [{:?}]",
-        profile, deploy_file_dir
+        "Received the following parameters: profile: [{:?}], dir: [{:?}], file: [{:?}], options: [{:?}]",
+        profile, deploy_file_dir, file, options
     );
 
-    let deployment = Deployment::new(&deploy_file_dir, None)?;
+    let file_name = deployment_file_name(file);
+    let deployments = Deployment::all_in(&deploy_file_dir, &file_name)?;
     info!("Deployment file found. Checking deployment");
 
-    let values_default = values::default(deploy_file_dir.clone())?;
-    let values_profile = values::profile(deploy_file_dir, &profile)?;
+    let mut releases = Vec::new();
+    for deployment in deployments {
+        let values_default = values::default(deploy_file_dir.clone(), deployment.variant())?;
+        let values_profile =
+            values::profile(deploy_file_dir.clone(), &profile, deployment.variant())?;
+        releases.push((deployment, values_default, values_profile));
+    }
+
+    for (deployment, values_default, values_profile) in releases {
+        let name = deployment.release_name().to_string();
+        let namespace = deployment.chart.namespace.clone();
 
-    let prompt = format!(
-        "Do you really want to deploy? profile: {profile:?} namespace: {}: [Y/N]",
-        deployment.chart.namespace
-    );
-    if !no_verify && !user_confirmation(&prompt)? {
-        return Ok(());
+        let prompt = format!(
+            "Do you really want to deploy {name}? profile: {profile:?} namespace: {namespace}: [Y/N]"
+        );
+        if !options.no_verify && !user_confirmation(&prompt)? {
+            continue;
+        }
+
+        let _lock = lock::acquire(&name, &namespace, options.wait_for_lock)?;
+        if options.unlock_pending {
+            unlock::recover(&name, &namespace, options.no_verify)?;
+        }
+        let run_tests = options.run_tests || deployment.chart.run_tests();
+        let restore_point = if options.auto_rollback || deployment.chart.auto_rollback() {
+            Some(rollback::restore_point(&name, &namespace)?)
+        } else {
+            None
+        };
+
+        let context = HookContext::new(&deployment, profile.as_deref(), &deploy_file_dir);
+        hooks::run(
+            HookStage::PreDeploy,
+            deployment.chart.hooks(HookStage::PreDeploy),
+            &context,
+        )?;
+
+        message!(
+            "Deploying {name}. This can take up to {}",
+            settings::get().timeout
+        );
+        let execution = create_deploy(
+            &deployment,
+            values_default.path(),
+            values_profile.as_ref().map(|p| p.path()),
+        )
+        .execute()?;
+        let exit_code = execution.exit_code;
+        let succeeded = exit_code == Some(0);
+        let values: Vec<_> = [Some(&values_default), values_profile.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(|values| (values.path(), values.source()))
+            .collect();
+        audit::record(
+            &Audited {
+                profile: profile.as_deref(),
+                chart_version: deployment.chart.version(),
+                values: &values,
+                ..Audited::new(ReleaseAction::Deploy, &name, &namespace)
+            },
+            &execution,
+        );
+        output::record_executed(&name, &namespace, ReleaseAction::Deploy, execution);
+
+        let verified = if succeeded {
+            verify(&name, &namespace, options.health_check, run_tests)
+        } else {
+            Ok(())
+        };
+        if let Some(point) = restore_point.filter(|_| !succeeded || verified.is_err()) {
+            message!("Deploy of {name} failed");
+            let restored = rollback::restore(&name, &namespace, point)?;
+            let cause = match verified {
+                Err(err) => err.to_string(),
+                Ok(()) => exit_reason(exit_code),
+            };
+            return Err(Error::DeployFailed(name, format!("{cause}. {restored}")));
+        }
+        verified?;
+        if !succeeded {
+            return Err(Error::DeployFailed(name, exit_reason(exit_code)));
+        }
+
+        hooks::run(
+            HookStage::PostDeploy,
+            deployment.chart.hooks(HookStage::PostDeploy),
+            &context,
+        )?;
     }
 
-    println!("Deploying chart. This can take up to 2 minutes");
-    create_deploy(deployment, values_default, values_profile).execute()?;
+    Ok(())
+}
+
+/// Why helm failed, as far as its exit code tells
+fn exit_reason(exit_code: Option<i32>) -> String {
+    match exit_code {
+        Some(code) => format!("helm exited with code {code}"),
+        None => String::from("helm was terminated by a signal"),
+    }
+}
+
+/// Checks the health and runs the tests of a deployed release, if requested
+fn verify(
+    name: &str,
+    namespace: &str,
+    health_check: Option<Duration>,
+    run_tests: bool,
+) -> Result<()> {
+    if let Some(window) = health_check {
+        check_health(name, namespace, window)?;
+    }
+    if run_tests && !test::run_tests(name, namespace)? {
+        return Err(Error::TestsFailed(vec![name.to_string()]));
+    }
 
     Ok(())
 }
 
+fn check_health(name: &str, namespace: &str, window: Duration) -> Result<()> {
+    message!("Verifying the health of {name}");
+    let manifest = health::create_get_manifest(name, namespace).output()?;
+    let checks = health::verify(name, namespace, &manifest, window, &mut |command| {
+        command.output()
+    })?;
+
+    let result = health::ensure_healthy(name, &checks);
+    output::record_health(name, namespace, checks);
+    if result.is_ok() {
+        message!("{name} is healthy");
+    }
+
+    result
+}
+
 fn create_deploy(
-    deployment: Deployment,
-    values_default: PathBuf,
-    values_profile: Option<PathBuf>,
+    deployment: &Deployment,
+    values_default: &Path,
+    values_profile: Option<&Path>,
 ) -> Command {
-    let mut command = Command::new("helm");
+    let mut command = Command::helm();
     command
+        .release(deployment.release_name())
         .args(["upgrade", "--install"])
         .arg("--wait")
-        .args(["--timeout", "2m0s"])
+        .args(["--timeout", &settings::get().timeout])
         .args(["-f", values_default.to_str().unwrap()]);
 
     if let Some(p) = values_profile {
@@ -52,7 +211,7 @@ fn create_deploy(
 
 #[cfg(test)]
 mod tests {
-    use std::{error::Error, path::PathBuf, str::FromStr};
+    use std::{error::Error, path::Path};
 
     use crate::deploy_config::test_fixtures::deployment;
 
@@ -66,10 +225,10 @@ mod tests {
         // given
         let deployment = deployment();
 
-        let values_default = PathBuf::from_str("values-default.yaml")?;
+        let values_default = Path::new("values-default.yaml");
 
         // when
-        let result = create_deploy(deployment, values_default, None);
+        let result = create_deploy(&deployment, values_default, None);
 
         // then
         assert_eq!(result.get_program(), "helm");
@@ -94,11 +253,11 @@ mod tests {
         // given
         let deployment = deployment();
 
-        let values_default = PathBuf::from_str("values-default.yaml")?;
-        let values_profile = PathBuf::from_str("values-test.yaml")?;
+        let values_default = Path::new("values-default.yaml");
+        let values_profile = Path::new("values-test.yaml");
 
         // when
-        let result = create_deploy(deployment, values_default, Some(values_profile));
+        let result = create_deploy(&deployment, values_default, Some(values_profile));
 
         // then
         assert_eq!(result.get_program(), "helm");
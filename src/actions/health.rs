@@ -0,0 +1,396 @@
+use std::{collections::BTreeMap, thread, time::Duration};
+
+use serde::Serialize;
+use serde_json::Value;
+use tracing::{debug, info};
+use yaml_rust2::YamlLoader;
+
+use crate::{Error, Result, command::Command, settings};
+
+/// Runs a kubectl command and returns its stdout. Fails on a non-zero exit code
+pub(crate) type Kubectl<'a> = dyn FnMut(Command) -> Result<String> + 'a;
+
+/// Kinds that are rolled out and can be watched with kubectl rollout status
+const ROLLOUT_KINDS: [&str; 3] = ["Deployment", "StatefulSet", "DaemonSet"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum CheckKind {
+    Rollout,
+    Restarts,
+    Job,
+}
+
+/// Result of a single check of a workload
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct HealthCheck {
+    pub(crate) check: CheckKind,
+    pub(crate) resource: String,
+    pub(crate) healthy: bool,
+    pub(crate) details: String,
+}
+
+#[derive(Debug, PartialEq)]
+struct Workload {
+    kind: String,
+    name: String,
+    namespace: String,
+}
+
+/// Verifies the workloads of a deployed release:
+/// - every Deployment, StatefulSet and DaemonSet finished its rollout
+/// - no container of the release restarted within the window
+/// - no Job failed
+///
+/// Pods are found by the app.kubernetes.io/instance label helm charts set
+pub(crate) fn verify(
+    name: &str,
+    namespace: &str,
+    manifest: &str,
+    window: Duration,
+    kubectl: &mut Kubectl,
+) -> Result<Vec<HealthCheck>> {
+    let workloads = parse_workloads(manifest, namespace)?;
+    debug!("Found the following workloads of {name}: {workloads:?}");
+
+    let mut checks = Vec::new();
+    for workload in workloads
+        .iter()
+        .filter(|w| ROLLOUT_KINDS.contains(&w.kind.as_str()))
+    {
+        checks.push(check_rollout(workload, kubectl));
+    }
+
+    let selector = format!("app.kubernetes.io/instance={name}");
+    let before = container_states(&kubectl(create_get_pods(namespace, &selector))?)?;
+    info!("Watching the pods of {name} for restarts for {window:?}");
+    thread::sleep(window);
+    let after = container_states(&kubectl(create_get_pods(namespace, &selector))?)?;
+    checks.extend(check_restarts(&before, &after));
+
+    for workload in workloads.iter().filter(|w| w.kind == "Job") {
+        checks.push(check_job(workload, kubectl)?);
+    }
+
+    Ok(checks)
+}
+
+/// Turns the unhealthy checks into an error
+pub(crate) fn ensure_healthy(name: &str, checks: &[HealthCheck]) -> Result<()> {
+    let problems: Vec<String> = checks
+        .iter()
+        .filter(|check| !check.healthy)
+        .map(|check| format!("{}: {}", check.resource, check.details))
+        .collect();
+    if !problems.is_empty() {
+        return Err(Error::UnhealthyRelease(name.to_string(), problems));
+    }
+
+    Ok(())
+}
+
+fn parse_workloads(manifest: &str, namespace: &str) -> Result<Vec<Workload>> {
+    let documents =
+        YamlLoader::load_from_str(manifest).map_err(|err| Error::YamlError(err.to_string()))?;
+
+    Ok(documents
+        .iter()
+        .filter_map(|document| {
+            let kind = document["kind"].as_str()?;
+            let name = document["metadata"]["name"].as_str()?;
+            let namespace = document["metadata"]["namespace"]
+                .as_str()
+                .unwrap_or(namespace);
+            Some(Workload {
+                kind: kind.to_string(),
+                name: name.to_string(),
+                namespace: namespace.to_string(),
+            })
+        })
+        .collect())
+}
+
+fn check_rollout(workload: &Workload, kubectl: &mut Kubectl) -> HealthCheck {
+    let resource = format!("{}/{}", workload.kind.to_lowercase(), workload.name);
+    let (healthy, details) = match kubectl(create_rollout_status(workload)) {
+        Ok(output) => (true, output.trim().to_string()),
+        Err(err) => (false, err.to_string()),
+    };
+
+    HealthCheck {
+        check: CheckKind::Rollout,
+        resource,
+        healthy,
+        details,
+    }
+}
+
+/// Restart count and waiting reason of every container, by pod/container
+fn container_states(pods: &str) -> Result<BTreeMap<String, (u64, Option<String>)>> {
+    let pods: Value = serde_json::from_str(pods)?;
+    let mut states = BTreeMap::new();
+
+    for pod in pods["items"].as_array().into_iter().flatten() {
+        let pod_name = pod["metadata"]["name"].as_str().unwrap_or_default();
+        for container in pod["status"]["containerStatuses"]
+            .as_array()
+            .into_iter()
+            .flatten()
+        {
+            let container_name = container["name"].as_str().unwrap_or_default();
+            let restarts = container["restartCount"].as_u64().unwrap_or_default();
+            let waiting = container["state"]["waiting"]["reason"]
+                .as_str()
+                .map(str::to_string);
+            states.insert(format!("{pod_name}/{container_name}"), (restarts, waiting));
+        }
+    }
+
+    Ok(states)
+}
+
+fn check_restarts(
+    before: &BTreeMap<String, (u64, Option<String>)>,
+    after: &BTreeMap<String, (u64, Option<String>)>,
+) -> Vec<HealthCheck> {
+    after
+        .iter()
+        .map(|(container, (restarts, waiting))| {
+            let previous = before.get(container).map(|(r, _)| *r).unwrap_or(0);
+            let restarted = restarts.saturating_sub(previous);
+            let crash_looping = waiting.as_deref() == Some("CrashLoopBackOff");
+
+            let details = if crash_looping {
+                format!("container is in CrashLoopBackOff after {restarts} restarts")
+            } else if restarted > 0 {
+                format!("container restarted {restarted} times within the window")
+            } else {
+                format!("no restarts within the window, {restarts} in total")
+            };
+
+            HealthCheck {
+                check: CheckKind::Restarts,
+                resource: format!("pod/{container}"),
+                healthy: !crash_looping && restarted == 0,
+                details,
+            }
+        })
+        .collect()
+}
+
+fn check_job(workload: &Workload, kubectl: &mut Kubectl) -> Result<HealthCheck> {
+    let job: Value = serde_json::from_str(&kubectl(create_get_job(workload))?)?;
+
+    let failed = job["status"]["failed"].as_u64().unwrap_or_default();
+    let condition = job["status"]["conditions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|c| c["type"] == "Failed" && c["status"] == "True");
+
+    let (healthy, details) = match condition {
+        Some(condition) => (
+            false,
+            format!(
+                "job failed: {}",
+                condition["message"].as_str().unwrap_or("no message")
+            ),
+        ),
+        None if failed > 0 => (false, format!("{failed} pods of the job failed")),
+        None => (true, String::from("job did not fail")),
+    };
+
+    Ok(HealthCheck {
+        check: CheckKind::Job,
+        resource: format!("job/{}", workload.name),
+        healthy,
+        details,
+    })
+}
+
+fn create_rollout_status(workload: &Workload) -> Command {
+    let mut command = Command::kubectl();
+    command
+        .args(["rollout", "status"])
+        .arg(format!(
+            "{}/{}",
+            workload.kind.to_lowercase(),
+            workload.name
+        ))
+        .args(["--namespace", &workload.namespace])
+        .arg(format!("--timeout={}", settings::get().timeout));
+
+    command
+}
+
+fn create_get_pods(namespace: &str, selector: &str) -> Command {
+    let mut command = Command::kubectl();
+    command
+        .args(["get", "pods"])
+        .args(["--namespace", namespace])
+        .args(["--selector", selector])
+        .args(["-o", "json"]);
+
+    command
+}
+
+fn create_get_job(workload: &Workload) -> Command {
+    let mut command = Command::kubectl();
+    command
+        .args(["get", "job", &workload.name])
+        .args(["--namespace", &workload.namespace])
+        .args(["-o", "json"]);
+
+    command
+}
+
+/// Fetches the manifest the release was deployed with
+pub(crate) fn create_get_manifest(name: &str, namespace: &str) -> Command {
+    let mut command = Command::helm();
+    command
+        .args(["get", "manifest", name])
+        .args(["--namespace", namespace]);
+
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{CheckKind, HealthCheck, create_rollout_status, ensure_healthy, verify};
+    use crate::{Error, command::Command};
+
+    type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+    const MANIFEST: &str = "
+---
+kind: Service
+metadata:
+  name: web
+---
+kind: Deployment
+metadata:
+  name: web
+---
+kind: Job
+metadata:
+  name: migrate
+  namespace: jobs
+";
+
+    const PODS_BEFORE: &str = r#"{"items": [{
+        "metadata": {"name": "web-1"},
+        "status": {"containerStatuses": [{"name": "nginx", "restartCount": 1, "state": {"running": {}}}]}
+    }]}"#;
+
+    const PODS_AFTER: &str = r#"{"items": [{
+        "metadata": {"name": "web-1"},
+        "status": {"containerStatuses": [{"name": "nginx", "restartCount": 3, "state": {"waiting": {"reason": "CrashLoopBackOff"}}}]}
+    }]}"#;
+
+    /// Answers kubectl commands like a cluster in which the pods crash and the job failed
+    fn scripted_kubectl() -> impl FnMut(Command) -> crate::Result<String> {
+        let mut pods = vec![PODS_AFTER, PODS_BEFORE];
+        move |command: Command| {
+            let command = command.to_string();
+            if command.starts_with("kubectl rollout status") {
+                Ok(String::from("deployment \"web\" successfully rolled out\n"))
+            } else if command.starts_with("kubectl get pods") {
+                Ok(pods.pop().unwrap_or(PODS_AFTER).to_string())
+            } else if command.starts_with("kubectl get job migrate --namespace jobs") {
+                Ok(String::from(
+                    r#"{"status": {"failed": 1, "conditions": [{"type": "Failed", "status": "True", "message": "BackoffLimitExceeded"}]}}"#,
+                ))
+            } else {
+                Err(Error::CommandFailed(command, Some(1)))
+            }
+        }
+    }
+
+    #[test]
+    fn report_crash_looping_pods_and_failed_jobs() -> TestResult {
+        // given
+        let mut kubectl = scripted_kubectl();
+
+        // when
+        let result = verify("web", "dev", MANIFEST, Duration::ZERO, &mut kubectl)?;
+
+        // then
+        assert_eq!(
+            result,
+            [
+                HealthCheck {
+                    check: CheckKind::Rollout,
+                    resource: String::from("deployment/web"),
+                    healthy: true,
+                    details: String::from("deployment \"web\" successfully rolled out"),
+                },
+                HealthCheck {
+                    check: CheckKind::Restarts,
+                    resource: String::from("pod/web-1/nginx"),
+                    healthy: false,
+                    details: String::from("container is in CrashLoopBackOff after 3 restarts"),
+                },
+                HealthCheck {
+                    check: CheckKind::Job,
+                    resource: String::from("job/migrate"),
+                    healthy: false,
+                    details: String::from("job failed: BackoffLimitExceeded"),
+                },
+            ]
+        );
+        assert!(matches!(
+            ensure_healthy("web", &result),
+            Err(Error::UnhealthyRelease(_, problems)) if problems.len() == 2
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn failed_rollout_unhealthy() -> TestResult {
+        // given
+        let manifest = "kind: Deployment\nmetadata:\n  name: web\n";
+        let mut kubectl = |command: Command| {
+            let command = command.to_string();
+            if command.starts_with("kubectl get pods") {
+                Ok(String::from(r#"{"items": []}"#))
+            } else {
+                Err(Error::CommandFailed(command, Some(1)))
+            }
+        };
+
+        // when
+        let result = verify("web", "dev", manifest, Duration::ZERO, &mut kubectl)?;
+
+        // then
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].check, CheckKind::Rollout);
+        assert!(!result[0].healthy);
+
+        Ok(())
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn rollout_status_of_workload() {
+        // given
+        let workload = super::Workload {
+            kind: String::from("StatefulSet"),
+            name: String::from("db"),
+            namespace: String::from("dev"),
+        };
+
+        // when
+        let result = create_rollout_status(&workload);
+
+        // then
+        assert_eq!(result.get_program(), "kubectl");
+        assert_eq!(result.get_args(), [
+            "rollout", "status", "statefulset/db",
+            "--namespace", "dev",
+            "--timeout=2m0s",
+        ]);
+    }
+}
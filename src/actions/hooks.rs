@@ -0,0 +1,133 @@
+use std::path::Path;
+
+use crate::{
+    Error, Result,
+    command::Command,
+    deploy_config::{Deployment, HookStage},
+    output::{self, ReleaseAction, message},
+};
+
+/// Release information passed to hooks as environment variables
+#[derive(Debug)]
+pub(super) struct HookContext<'a> {
+    pub(super) release: &'a str,
+    pub(super) namespace: &'a str,
+    pub(super) profile: Option<&'a str>,
+    pub(super) chart_version: Option<&'a str>,
+    /// Directory of the release. Hooks are run in it
+    pub(super) dir: &'a Path,
+}
+
+impl<'a> HookContext<'a> {
+    pub(super) fn new(deployment: &'a Deployment, profile: Option<&'a str>, dir: &'a Path) -> Self {
+        Self {
+            release: deployment.release_name(),
+            namespace: &deployment.chart.namespace,
+            profile,
+            chart_version: deployment.chart.version(),
+            dir,
+        }
+    }
+}
+
+/// Runs the hooks of the stage one after another. Stops at the first failing hook
+pub(super) fn run(stage: HookStage, hooks: &[String], context: &HookContext) -> Result<()> {
+    for hook in hooks {
+        message!("Running {stage} hook of {}: {hook}", context.release);
+        let execution = create_hook(hook, context).execute()?;
+        let succeeded = execution.exit_code == Some(0);
+        output::record_executed(
+            context.release,
+            context.namespace,
+            ReleaseAction::Hook,
+            execution,
+        );
+
+        if !succeeded {
+            return Err(Error::HookFailed(
+                stage.to_string(),
+                context.release.to_string(),
+                hook.clone(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn create_hook(hook: &str, context: &HookContext) -> Command {
+    let mut command = Command::new("sh");
+    command
+        .release(context.release)
+        .args(["-c", hook])
+        .current_dir(context.dir)
+        .env("SHIPPR_RELEASE", context.release)
+        .env("SHIPPR_NAMESPACE", context.namespace)
+        .env("SHIPPR_PROFILE", context.profile.unwrap_or_default())
+        .env(
+            "SHIPPR_CHART_VERSION",
+            context.chart_version.unwrap_or_default(),
+        );
+
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsStr, path::Path};
+
+    use super::{HookContext, create_hook, run};
+    use crate::{Error, deploy_config::HookStage};
+
+    type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+    fn context(dir: &Path) -> HookContext<'_> {
+        HookContext {
+            release: "TestRelease",
+            namespace: "TestNamespace",
+            profile: Some("prod"),
+            chart_version: None,
+            dir,
+        }
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn hook_runs_in_release_dir_with_release_information() {
+        // given
+        let context = context(Path::new("releases/nginx"));
+
+        // when
+        let result = create_hook("./migrate.sh", &context);
+
+        // then
+        assert_eq!(result.get_program(), "sh");
+        assert_eq!(result.get_args(), ["-c", "./migrate.sh"]);
+        assert_eq!(result.get_current_dir(), Some(Path::new("releases/nginx")));
+        assert_eq!(result.get_envs(), [
+            (OsStr::new("SHIPPR_CHART_VERSION"), Some(OsStr::new(""))),
+            (OsStr::new("SHIPPR_NAMESPACE"), Some(OsStr::new("TestNamespace"))),
+            (OsStr::new("SHIPPR_PROFILE"), Some(OsStr::new("prod"))),
+            (OsStr::new("SHIPPR_RELEASE"), Some(OsStr::new("TestRelease"))),
+        ]);
+    }
+
+    #[test]
+    fn failing_hook_stops_remaining_hooks() -> TestResult {
+        // given
+        let dir = tempfile::tempdir()?;
+        let hooks = [String::from("exit 3"), String::from("touch never-run")];
+
+        // when
+        let result = run(HookStage::PreDeploy, &hooks, &context(dir.path()));
+
+        // then
+        assert!(matches!(
+            result,
+            Err(Error::HookFailed(stage, _, hook)) if stage == "preDeploy" && hook == "exit 3"
+        ));
+        assert!(!dir.path().join("never-run").exists());
+
+        Ok(())
+    }
+}
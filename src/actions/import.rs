@@ -0,0 +1,275 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use tracing::{debug, info};
+
+use crate::{
+    Error, Result,
+    actions::{DeploymentFormat, cleanup::action::list_released},
+    command::Command,
+    deploy_config::LOCATION_PLACEHOLDER,
+    output::message,
+    settings::deployment_file_name,
+};
+
+/// Chart information of a deployed release
+#[derive(Debug, PartialEq, Deserialize)]
+struct Metadata {
+    chart: String,
+    version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResult {
+    name: String,
+    version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Repository {
+    name: String,
+    url: String,
+}
+
+/// Generates a release directory for every release deployed in the namespace,
+/// or in all namespaces if none is given.
+/// With all namespaces, the release directories are grouped by namespace
+pub fn import(namespace: Option<String>, dir: PathBuf) -> Result<()> {
+    debug!("Received the following parameters: namespace: [{namespace:?}], dir: [{dir:?}]");
+
+    if dir.exists() && !dir.is_dir() {
+        return Err(Error::InvalidDirectory);
+    }
+
+    let releases = list_released(namespace.as_deref())?;
+    if releases.is_empty() {
+        message!("Nothing to import");
+        return Ok(());
+    }
+
+    let targets: Vec<(String, String, PathBuf)> = releases
+        .into_iter()
+        .map(|(name, ns)| {
+            let target = target_dir(&dir, &name, &ns, namespace.is_none());
+            (name, ns, target)
+        })
+        .collect();
+
+    let existing: Vec<String> = targets
+        .iter()
+        .filter(|(_, _, target)| target.exists())
+        .map(|(_, _, target)| target.display().to_string())
+        .collect();
+    if !existing.is_empty() {
+        return Err(Error::ImportTargetsExist(existing));
+    }
+
+    let file_name = format!("{}.yaml", deployment_file_name(None).as_str());
+    let repositories = find_repositories();
+    for (name, namespace, target) in targets {
+        import_release(&name, &namespace, &target, &file_name, &repositories)?;
+        message!("Imported {name} into {}", target.display());
+    }
+
+    Ok(())
+}
+
+fn target_dir(dir: &Path, name: &str, namespace: &str, group_by_namespace: bool) -> PathBuf {
+    if group_by_namespace {
+        dir.join(namespace).join(name)
+    } else {
+        dir.join(name)
+    }
+}
+
+fn import_release(
+    name: &str,
+    namespace: &str,
+    target: &Path,
+    file_name: &str,
+    repositories: &[Repository],
+) -> Result<()> {
+    let metadata: Metadata = serde_json::from_str(&create_get_metadata(name, namespace).output()?)?;
+    info!("Found the chart of {name}: {metadata:?}");
+
+    let values = create_get_values(name, namespace).output()?;
+
+    let location = find_repository_url(&metadata, repositories);
+    if location.is_none() {
+        message!(
+            "Could not determine the repository of the chart {}. Replace the placeholder of location.repo in the deployment file of {name}",
+            metadata.chart
+        );
+    }
+
+    fs::create_dir_all(target)?;
+    fs::write(target.join("values-default.yaml"), user_values(&values))?;
+    fs::write(
+        target.join(file_name),
+        DeploymentFormat::Yaml.render(&deployment(&metadata, namespace, location))?,
+    )?;
+    info!("Created release directory: {target:?}");
+
+    Ok(())
+}
+
+/// An unknown repository is written as a placeholder, which the validation reports until it is replaced
+fn deployment(metadata: &Metadata, namespace: &str, location: Option<&str>) -> serde_json::Value {
+    serde_json::json!({
+        "name": metadata.chart,
+        "version": metadata.version,
+        "namespace": namespace,
+        "location": { "repo": location.unwrap_or(LOCATION_PLACEHOLDER) },
+    })
+}
+
+/// Helm prints null if no values were supplied by the user
+fn user_values(values: &str) -> &str {
+    if values.trim() == "null" { "" } else { values }
+}
+
+/// Best effort: Looks for the chart in the locally configured helm repositories
+fn find_repository_url<'a>(metadata: &Metadata, repositories: &'a [Repository]) -> Option<&'a str> {
+    let results = create_search_repo(metadata).output().ok()?;
+    let results: Vec<SearchResult> = serde_json::from_str(&results).ok()?;
+
+    results
+        .iter()
+        .filter(|result| result.version == metadata.version)
+        .filter_map(|result| result.name.split_once('/'))
+        .filter(|(_, chart)| *chart == metadata.chart)
+        .find_map(|(alias, _)| {
+            repositories
+                .iter()
+                .find(|repository| repository.name == alias)
+        })
+        .map(|repository| repository.url.as_str())
+}
+
+/// Empty if helm lists no repositories, e.g. because none is configured
+fn find_repositories() -> Vec<Repository> {
+    create_list_repositories()
+        .output()
+        .ok()
+        .and_then(|repositories| serde_json::from_str(&repositories).ok())
+        .unwrap_or_default()
+}
+
+fn create_get_metadata(name: &str, namespace: &str) -> Command {
+    let mut command = Command::helm();
+    command
+        .args(["get", "metadata", name])
+        .args(["--namespace", namespace])
+        .args(["-o", "json"]);
+
+    command
+}
+
+fn create_get_values(name: &str, namespace: &str) -> Command {
+    let mut command = Command::helm();
+    command
+        .args(["get", "values", name])
+        .args(["--namespace", namespace])
+        .args(["-o", "yaml"]);
+
+    command
+}
+
+fn create_search_repo(metadata: &Metadata) -> Command {
+    let mut command = Command::helm();
+    command
+        .args(["search", "repo", &metadata.chart])
+        .args(["--version", &metadata.version])
+        .args(["-o", "json"]);
+
+    command
+}
+
+fn create_list_repositories() -> Command {
+    let mut command = Command::helm();
+    command.args(["repo", "list"]).args(["-o", "json"]);
+
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{
+        Metadata, create_get_metadata, create_get_values, deployment, target_dir, user_values,
+    };
+    use crate::deploy_config::LOCATION_PLACEHOLDER;
+
+    type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+    #[rustfmt::skip]
+    #[test]
+    fn get_metadata_and_values_of_release() {
+        // when
+        let metadata = create_get_metadata("nginx-release", "dev");
+        let values = create_get_values("nginx-release", "dev");
+
+        // then
+        assert_eq!(metadata.get_program(), "helm");
+        assert_eq!(metadata.get_args(), [
+            "get", "metadata", "nginx-release",
+            "--namespace", "dev",
+            "-o", "json",
+        ]);
+        assert_eq!(values.get_args(), [
+            "get", "values", "nginx-release",
+            "--namespace", "dev",
+            "-o", "yaml",
+        ]);
+    }
+
+    #[test]
+    fn target_grouped_by_namespace_for_all_namespaces() {
+        // when
+        let single = target_dir(Path::new("releases"), "nginx", "dev", false);
+        let grouped = target_dir(Path::new("releases"), "nginx", "dev", true);
+
+        // then
+        assert_eq!(single, PathBuf::from("releases/nginx"));
+        assert_eq!(grouped, PathBuf::from("releases/dev/nginx"));
+    }
+
+    #[test]
+    fn deployment_from_metadata() -> TestResult {
+        // given
+        let metadata: Metadata = serde_json::from_str(
+            r#"{"name":"nginx-release","chart":"nginx","version":"15.0.0","appVersion":"1.25.0","namespace":"dev","revision":2,"status":"deployed"}"#,
+        )?;
+
+        // when
+        let known = deployment(&metadata, "dev", Some("https://charts.example.com"));
+        let unknown = deployment(&metadata, "dev", None);
+
+        // then
+        assert_eq!(
+            known,
+            serde_json::json!({
+                "name": "nginx",
+                "version": "15.0.0",
+                "namespace": "dev",
+                "location": { "repo": "https://charts.example.com" },
+            })
+        );
+        assert_eq!(
+            unknown["location"],
+            serde_json::json!({ "repo": LOCATION_PLACEHOLDER })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn user_values_without_null() {
+        assert_eq!(user_values("null\n"), "");
+        assert_eq!(user_values("replicas: 2\n"), "replicas: 2\n");
+    }
+}
@@ -1,27 +1,209 @@
 use tracing::{debug, info};
 
-use crate::{Error, Result};
-use std::{fs, io::Write};
+use crate::{
+    Error, Result,
+    command::Command,
+    deploy_config::{ChartLocation, DeployChart, SCHEMA_URL},
+    io::{is_interactive, user_input},
+    output::message,
+    settings::deployment_file_name,
+    yaml,
+};
+use serde::Serialize;
+use std::{fs, io::Write, path::Path};
 
-pub fn initialize_configuration(deployment_name: String) -> Result<()> {
-    debug!("Received the following parameters: name: [{deployment_name:?}");
+/// Format of the generated deployment file
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DeploymentFormat {
+    #[default]
+    Yaml,
+    Toml,
+    Json,
+}
 
-    create_dir(&deployment_name)?;
-    create_file(&deployment_name, "values-default.yaml", None)?;
+/// Settings of the generated deployment. Missing settings are prompted for if possible
+#[derive(Debug, Default)]
+pub struct InitOptions {
+    pub chart: Option<String>,
+    pub location: Option<ChartLocation>,
+    pub version: Option<String>,
+    pub namespace: Option<String>,
+    pub profiles: Option<Vec<String>>,
+    pub format: DeploymentFormat,
+    /// Fills values-default.yaml with the default values of the chart
+    pub from_chart: bool,
+    /// Comments out the default values of the chart
+    pub commented: bool,
+}
+
+/// Answers a prompt. Returns None if the user cannot be asked
+type Prompt<'a> = dyn FnMut(&str, Option<&str>) -> Result<Option<String>> + 'a;
+
+pub fn initialize_configuration(deployment_name: String, options: InitOptions) -> Result<()> {
+    debug!(
+        "Received the following parameters: name: [{deployment_name:?}], options: [{options:?}]"
+    );
+
+    if deployment_name.contains("/") {
+        return Err(Error::ReleaseNameIsPath);
+    }
+
+    let format = options.format;
+    let (from_chart, commented) = (options.from_chart, options.commented);
+    let file_name = format!(
+        "{}.{}",
+        deployment_file_name(None).as_str(),
+        format.extension()
+    );
 
-    let default_deployment_file = create_default_deployment(&deployment_name);
+    let interactive = is_interactive();
+    let mut prompt = |prompt: &str, default: Option<&str>| -> Result<Option<String>> {
+        if interactive {
+            user_input(prompt, default).map(Some)
+        } else {
+            Ok(None)
+        }
+    };
+    let (chart, profiles) = complete_options(&deployment_name, options, &mut prompt)?;
+    chart.validate(Path::new(&file_name), &deployment_name)?;
+
+    let values_default = if from_chart {
+        message!("Fetching the default values of the chart");
+        let values = create_show_values(&chart).output()?;
+        Some(if commented {
+            comment_out(&values)
+        } else {
+            values
+        })
+    } else {
+        None
+    };
+
+    create_dir(&deployment_name)?;
     create_file(
         &deployment_name,
-        "deployment.yaml",
-        Some(&default_deployment_file),
+        "values-default.yaml",
+        values_default.as_deref(),
     )?;
+    for profile in &profiles {
+        create_file(&deployment_name, &format!("values-{profile}.yaml"), None)?;
+    }
+
+    let deployment_file = format.render(&chart)?;
+    create_file(&deployment_name, &file_name, Some(&deployment_file))?;
 
-    println!("Created deployment files for {deployment_name}");
-    println!("Please configure the namespace, version, location and values before deploying");
+    message!("Created deployment files for {deployment_name}");
+    message!("Please configure the values before deploying");
 
     Ok(())
 }
 
+/// Fills every missing option by asking the user, or with its default
+fn complete_options(
+    deployment_name: &str,
+    options: InitOptions,
+    prompt: &mut Prompt,
+) -> Result<(DeployChart, Vec<String>)> {
+    let chart = match options.chart {
+        Some(chart) => chart,
+        None => prompt("Name of the chart:", Some(deployment_name))?
+            .unwrap_or_else(|| deployment_name.to_string()),
+    };
+
+    let location = match options.location {
+        Some(location) => location,
+        None => prompt(
+            "Location of the chart. A repository URL, an oci:// registry or a local path:",
+            None,
+        )?
+        .filter(|location| !location.is_empty())
+        .map(|location| location.parse())
+        .transpose()?
+        .ok_or(Error::MissingChartLocation)?,
+    };
+
+    let version = match options.version {
+        Some(version) => Some(version),
+        None => prompt(
+            "Version of the chart. Leave empty to always use the latest version:",
+            None,
+        )?
+        .filter(|version| !version.is_empty()),
+    };
+
+    let namespace = match options.namespace {
+        Some(namespace) => namespace,
+        None => prompt("Namespace:", Some("default"))?.unwrap_or_else(|| String::from("default")),
+    };
+
+    let profiles = match options.profiles {
+        Some(profiles) => profiles,
+        None => prompt("Profiles, comma separated. Leave empty for none:", None)?
+            .map(|profiles| profiles.split(',').map(|p| p.trim().to_string()).collect())
+            .unwrap_or_default(),
+    };
+    let profiles: Vec<String> = profiles.into_iter().filter(|p| !p.is_empty()).collect();
+    if profiles.iter().any(|p| p.contains("/")) {
+        return Err(Error::ProfileIsPath);
+    }
+
+    Ok((
+        DeployChart::new(chart, version, namespace, location),
+        profiles,
+    ))
+}
+
+fn create_show_values(chart: &DeployChart) -> Command {
+    let mut command = Command::helm();
+    command.args(["show", "values"]);
+    chart.append_chart_source(&mut command);
+
+    command
+}
+
+/// Comments out every line, so only the changed values have to be uncommented
+fn comment_out(values: &str) -> String {
+    values
+        .lines()
+        .map(|line| {
+            if line.trim().is_empty() {
+                line.to_string()
+            } else {
+                format!("# {line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        + "\n"
+}
+
+impl DeploymentFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            DeploymentFormat::Yaml => "yaml",
+            DeploymentFormat::Toml => "toml",
+            DeploymentFormat::Json => "json",
+        }
+    }
+
+    /// Renders the deployment file. YAML and TOML files reference the schema for editor support
+    pub(super) fn render<T: Serialize>(&self, chart: &T) -> Result<String> {
+        let content = match self {
+            DeploymentFormat::Yaml => format!(
+                "# yaml-language-server: $schema={SCHEMA_URL}\n{}",
+                yaml::to_yaml(&serde_json::to_value(chart)?)?
+            ),
+            DeploymentFormat::Toml => format!(
+                "#:schema {SCHEMA_URL}\n{}",
+                toml::to_string(chart).map_err(|err| Error::TomlError(err.to_string()))?
+            ),
+            DeploymentFormat::Json => format!("{}\n", serde_json::to_string_pretty(chart)?),
+        };
+
+        Ok(content)
+    }
+}
+
 fn create_dir(name: &str) -> Result<()> {
     if name.contains("/") {
         return Err(Error::ReleaseNameIsPath);
@@ -52,13 +234,174 @@ fn create_file(
     Ok(())
 }
 
-fn create_default_deployment(deployment_name: &str) -> String {
-    format!(
-        r#"name: {deployment_name}
-version: 1.0
-namespace: default
-location: # TODO replace with actual location
-  # repo: https://artifacthub.io/{deployment_name}
-  # local: /home/root/charts/{deployment_name}"#
-    )
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use crate::deploy_config::{ChartLocation, DeployChart};
+
+    use super::{DeploymentFormat, InitOptions, comment_out, complete_options, create_show_values};
+
+    type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+    fn chart() -> DeployChart {
+        DeployChart::new(
+            String::from("nginx"),
+            Some(String::from("1.2.3")),
+            String::from("dev"),
+            ChartLocation::Oci(String::from("oci://registry.example.com/charts")),
+        )
+    }
+
+    #[test]
+    fn complete_from_prompts() -> TestResult {
+        // given
+        let mut answers =
+            VecDeque::from(["", "https://charts.example.com", "^1.2", "dev", "dev, prod"]);
+        let mut prompt = |_: &str, default: Option<&str>| {
+            let answer = answers.pop_front().unwrap();
+            Ok(Some(match (answer, default) {
+                ("", Some(default)) => default.to_string(),
+                (answer, _) => answer.to_string(),
+            }))
+        };
+
+        // when
+        let (chart, profiles) =
+            complete_options("nginx-release", InitOptions::default(), &mut prompt)?;
+
+        // then
+        let chart = serde_json::to_value(chart)?;
+        assert_eq!(
+            chart,
+            serde_json::json!({
+                "name": "nginx-release",
+                "version": "^1.2",
+                "namespace": "dev",
+                "location": { "repo": "https://charts.example.com" },
+            })
+        );
+        assert_eq!(profiles, ["dev", "prod"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn complete_from_options_without_prompts() -> TestResult {
+        // given
+        let options = InitOptions {
+            chart: Some(String::from("nginx")),
+            location: Some(ChartLocation::Local(String::from("/charts/nginx"))),
+            ..Default::default()
+        };
+        let mut prompt = |_: &str, _: Option<&str>| Ok(None);
+
+        // when
+        let (chart, profiles) = complete_options("nginx-release", options, &mut prompt)?;
+
+        // then
+        let chart = serde_json::to_value(chart)?;
+        assert_eq!(
+            chart,
+            serde_json::json!({
+                "name": "nginx",
+                "namespace": "default",
+                "location": { "local": "/charts/nginx" },
+            })
+        );
+        assert!(profiles.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn complete_without_location_err() {
+        // given
+        let mut prompt = |_: &str, _: Option<&str>| Ok(None);
+
+        // when
+        let result = complete_options("nginx-release", InitOptions::default(), &mut prompt);
+
+        // then
+        assert!(matches!(
+            result.err(),
+            Some(crate::Error::MissingChartLocation)
+        ));
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn show_values_of_chart() {
+        // given
+        let chart = chart();
+
+        // when
+        let result = create_show_values(&chart);
+
+        // then
+        assert_eq!(result.get_program(), "helm");
+        assert_eq!(result.get_args(), [
+            "show", "values",
+            "oci://registry.example.com/charts/nginx",
+            "--version", "1.2.3",
+        ]);
+    }
+
+    #[test]
+    fn comment_out_keeps_empty_lines() {
+        // given
+        let values = "image:\n  tag: latest\n\n# Already a comment\nreplicas: 1";
+
+        // when
+        let result = comment_out(values);
+
+        // then
+        assert_eq!(
+            result,
+            "# image:\n#   tag: latest\n\n# # Already a comment\n# replicas: 1\n"
+        );
+    }
+
+    #[test]
+    fn render_yaml_with_schema() -> TestResult {
+        // when
+        let result = DeploymentFormat::Yaml.render(&chart())?;
+
+        // then
+        assert_eq!(
+            result,
+            format!(
+                "# yaml-language-server: $schema={}
+name: nginx
+version: 1.2.3
+namespace: dev
+location:
+  oci: \"oci://registry.example.com/charts\"
+",
+                crate::deploy_config::SCHEMA_URL
+            )
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn render_toml_and_json_readable() -> TestResult {
+        // when
+        let toml = DeploymentFormat::Toml.render(&chart())?;
+        let json = DeploymentFormat::Json.render(&chart())?;
+
+        // then
+        let toml: toml::Value = toml::from_str(&toml)?;
+        assert_eq!(toml["namespace"].as_str(), Some("dev"));
+        assert_eq!(
+            toml["location"]["oci"].as_str(),
+            Some("oci://registry.example.com/charts")
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&json)?;
+        assert_eq!(json["version"], "1.2.3");
+
+        Ok(())
+    }
 }
@@ -0,0 +1,252 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use tracing::{debug, info};
+use yaml_rust2::{Yaml, YamlLoader, yaml::Hash};
+
+use super::values::{self, ValuesFile};
+use crate::{
+    Error, Result,
+    command::Command,
+    deploy_config::*,
+    lint::{self, ChartValues},
+    output::{self, message},
+    policy::Severity,
+    settings::{self, deployment_file_name},
+};
+
+/// Lints the values files of every release defined in the directory against its chart.
+/// Without a profile, every profile with a values file is linted
+pub fn lint_values(
+    profile: Option<String>,
+    deploy_file_dir: PathBuf,
+    file: Option<String>,
+) -> Result<()> {
+    debug!(
+        "Received the following parameters: profile: [{profile:?}], dir: [{deploy_file_dir:?}], file: [{file:?}]"
+    );
+
+    let file_name = deployment_file_name(file);
+    for deployment in Deployment::all_in(&deploy_file_dir, &file_name)? {
+        let profiles = match &profile {
+            Some(profile) => vec![profile.clone()],
+            None => values::profiles(deployment.dir(), deployment.variant())?,
+        };
+
+        let values_default = values::default(deploy_file_dir.clone(), deployment.variant())?;
+        let mut values_profiles = Vec::new();
+        for profile in profiles {
+            values_profiles.extend(values::profile(
+                deploy_file_dir.clone(),
+                &Some(profile),
+                deployment.variant(),
+            )?);
+        }
+
+        lint_release(&deployment, &values_default, &values_profiles, true)?;
+    }
+
+    Ok(())
+}
+
+/// Reports every finding. Fails if any of them is an error.
+/// Findings are only printed by live lints
+pub(super) fn lint_release(
+    deployment: &Deployment,
+    values_default: &ValuesFile,
+    values_profiles: &[ValuesFile],
+    live: bool,
+) -> Result<()> {
+    let name = deployment.release_name();
+    let namespace = &deployment.chart.namespace;
+    let chart = chart_values(&deployment.chart)?;
+
+    let default = (file_name(values_default), load(values_default.path())?);
+    let mut profiles = Vec::new();
+    for values_profile in values_profiles {
+        profiles.push((file_name(values_profile), load(values_profile.path())?));
+    }
+    let profiles: Vec<_> = profiles
+        .iter()
+        .map(|(file, values)| (file.as_str(), values))
+        .collect();
+    let findings = lint::lint(
+        &chart,
+        (&default.0, &default.1),
+        &profiles,
+        &settings::get().lints,
+        deployment.chart.suppressed_lints(),
+    );
+
+    if live {
+        for finding in &findings {
+            message!("{finding}");
+        }
+    }
+    let errors: Vec<String> = findings
+        .iter()
+        .filter(|finding| finding.severity == Severity::Error)
+        .map(ToString::to_string)
+        .collect();
+    output::record_values(name, namespace, findings);
+
+    if !errors.is_empty() {
+        return Err(Error::InvalidValues(name.to_string(), errors));
+    }
+    if live {
+        message!("The values files of {name} match the chart");
+    }
+
+    Ok(())
+}
+
+/// Name of the file the values were written in, not of the converted file
+fn file_name(values: &ValuesFile) -> String {
+    values.source().file_name().map_or_else(
+        || values.source().display().to_string(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
+
+/// First document of the YAML file. Empty files have no values
+fn load(path: &Path) -> Result<Yaml> {
+    let content = fs::read_to_string(path)?;
+    let mut documents = YamlLoader::load_from_str(&content)
+        .map_err(|err| Error::YamlError(format!("{}: {err}", path.display())))?;
+
+    Ok(if documents.is_empty() {
+        Yaml::Hash(Hash::new())
+    } else {
+        documents.remove(0)
+    })
+}
+
+/// Reads the values, schema and dependencies of the chart.
+/// Charts that are not local are pulled into a temporary directory first
+fn chart_values(chart: &DeployChart) -> Result<ChartValues> {
+    if let Some(path) = chart.local_path() {
+        return read_chart(Path::new(path));
+    }
+
+    let dir = tempfile::tempdir()?;
+    info!("Pulling the chart {} into {:?}", chart.name(), dir.path());
+    create_pull(chart, dir.path()).output()?;
+
+    // helm untars the chart into a directory named like the chart
+    let pulled = fs::read_dir(dir.path())?
+        .next()
+        .ok_or_else(|| Error::UnreadableChart(chart.name().to_string()))??;
+    read_chart(&pulled.path())
+}
+
+fn read_chart(dir: &Path) -> Result<ChartValues> {
+    if !dir.is_dir() {
+        return Err(Error::UnreadableChart(dir.display().to_string()));
+    }
+
+    let values_file = dir.join("values.yaml");
+    let values = if values_file.exists() {
+        load(&values_file)?
+    } else {
+        Yaml::Hash(Hash::new())
+    };
+
+    let schema_file = dir.join("values.schema.json");
+    let schema = if schema_file.exists() {
+        Some(serde_json::from_str(&fs::read_to_string(schema_file)?)?)
+    } else {
+        None
+    };
+
+    let chart_file = dir.join("Chart.yaml");
+    let dependencies = if chart_file.exists() {
+        load(&chart_file)?["dependencies"]
+            .as_vec()
+            .into_iter()
+            .flatten()
+            .filter_map(|dependency| {
+                dependency["alias"]
+                    .as_str()
+                    .or_else(|| dependency["name"].as_str())
+            })
+            .map(str::to_string)
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    Ok(ChartValues {
+        values,
+        schema,
+        dependencies,
+    })
+}
+
+fn create_pull(chart: &DeployChart, dir: &Path) -> Command {
+    let mut command = Command::helm();
+    command.arg("pull");
+    chart.append_chart_source(&mut command);
+    command
+        .arg("--untar")
+        .args(["--untardir", dir.to_str().unwrap()]);
+
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use crate::deploy_config::test_fixtures::deployment;
+
+    use super::{create_pull, read_chart};
+
+    type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+    #[test]
+    fn chart_read_from_directory() -> TestResult {
+        // given
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("values.yaml"), "replicaCount: 1\n")?;
+        fs::write(
+            dir.path().join("values.schema.json"),
+            r#"{"properties": {"replicaCount": {"type": "integer"}}}"#,
+        )?;
+        fs::write(
+            dir.path().join("Chart.yaml"),
+            "name: web\ndependencies:\n  - name: redis\n  - name: postgresql\n    alias: db\n",
+        )?;
+
+        // when
+        let result = read_chart(dir.path())?;
+
+        // then
+        assert_eq!(result.values["replicaCount"].as_i64(), Some(1));
+        assert!(result.schema.is_some());
+        assert_eq!(result.dependencies, ["redis", "db"]);
+
+        Ok(())
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn pull_chart_into_directory() {
+        // given
+        let deployment = deployment();
+
+        // when
+        let result = create_pull(&deployment.chart, Path::new("/tmp/chart"));
+
+        // then
+        assert_eq!(result.get_program(), "helm");
+        assert_eq!(result.get_args(), [
+            "pull",
+            "TestChartName", "--repo", "TestRepo",
+            "--version", "TestVersion",
+            "--untar",
+            "--untardir", "/tmp/chart",
+        ]);
+    }
+}
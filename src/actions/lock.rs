@@ -0,0 +1,565 @@
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::Path,
+    process,
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use fs4::fs_std::FileExt;
+use jiff::{SignedDuration, Timestamp};
+use serde_json::{Value, json};
+use tempfile::NamedTempFile;
+use tracing::{info, warn};
+
+use crate::{Error, Result, audit, command::Command, output::message, settings};
+
+/// How often a locked release is checked while waiting for its lock
+const RETRY: Duration = Duration::from_secs(2);
+/// Used if the timeout of the settings cannot be parsed
+const DEFAULT_LEASE_DURATION: SignedDuration = SignedDuration::from_mins(10);
+
+/// Lock of a release, held while the release is changed. Released on drop
+#[derive(Debug)]
+pub(super) struct ReleaseLock {
+    /// Unlocked when closed
+    _file: File,
+    lease: Option<Lease>,
+    renewal: Option<Renewal>,
+}
+
+/// Lease in the cluster locking a release for every machine
+#[derive(Debug, Clone)]
+struct Lease {
+    name: String,
+    namespace: String,
+    /// Holder identity of this process
+    holder: String,
+}
+
+/// State of the lease of a release in the cluster
+#[derive(Debug, PartialEq)]
+enum LeaseState {
+    Missing,
+    /// Held by the holder until it expires
+    Held(String),
+    /// Can be taken over by replacing exactly this resource version
+    Expired(String),
+}
+
+/// Background thread renewing the lease while the lock is held. Stops once the sender is dropped
+#[derive(Debug)]
+struct Renewal {
+    stop: Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+impl Drop for ReleaseLock {
+    fn drop(&mut self) {
+        // Stops renewing first, so the lease is not renewed after it was released
+        if let Some(renewal) = self.renewal.take() {
+            drop(renewal.stop);
+            let _ = renewal.thread.join();
+        }
+        let Some(lease) = &self.lease else {
+            return;
+        };
+        if let Err(err) = release_lease(lease) {
+            warn!("Failed to release the lease {}: {err}", lease.name);
+        }
+    }
+}
+
+/// Locks the release on this machine and, if a lock namespace is configured, in the cluster.
+/// A locked release is retried until the wait is over
+pub(super) fn acquire(
+    release: &str,
+    namespace: &str,
+    wait: Option<Duration>,
+) -> Result<ReleaseLock> {
+    let deadline = Instant::now() + wait.unwrap_or_default();
+    let mut waiting = false;
+
+    loop {
+        match try_acquire(release, namespace) {
+            Err(Error::ReleaseLocked(_, holder)) if Instant::now() < deadline => {
+                if !waiting {
+                    message!("{release} is locked by {holder}. Waiting for the lock");
+                    waiting = true;
+                }
+                thread::sleep(RETRY);
+            }
+            result => return result,
+        }
+    }
+}
+
+fn try_acquire(release: &str, namespace: &str) -> Result<ReleaseLock> {
+    let dir = settings::state_dir()
+        .unwrap_or_else(|| env::temp_dir().join("shippr"))
+        .join("locks");
+    let file = lock_file(&dir, release, namespace)?;
+
+    let lease = match &settings::get().lock_namespace {
+        Some(lock_namespace) => Some(acquire_lease(release, namespace, lock_namespace)?),
+        None => None,
+    };
+    let renewal = lease.clone().map(renew_lease);
+
+    Ok(ReleaseLock {
+        _file: file,
+        lease,
+        renewal,
+    })
+}
+
+/// Locks the lock file of the release and writes the holder into it
+fn lock_file(dir: &Path, release: &str, namespace: &str) -> Result<File> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{namespace}.{release}.lock"));
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)?;
+
+    if !file.try_lock_exclusive()? {
+        let holder = fs::read_to_string(&path)
+            .ok()
+            .map(|holder| holder.trim().to_string())
+            .filter(|holder| !holder.is_empty())
+            .unwrap_or_else(|| String::from("another process"));
+        return Err(Error::ReleaseLocked(release.to_string(), holder));
+    }
+
+    file.set_len(0)?;
+    file.write_all(holder().as_bytes())?;
+    info!("Locked {release} with {path:?}");
+
+    Ok(file)
+}
+
+fn acquire_lease(release: &str, namespace: &str, lock_namespace: &str) -> Result<Lease> {
+    let lease = Lease {
+        name: format!("shippr-{namespace}-{release}"),
+        namespace: lock_namespace.to_string(),
+        holder: holder(),
+    };
+
+    let resource_version = match read_lease(&lease)? {
+        LeaseState::Held(holder) => {
+            return Err(Error::ReleaseLocked(release.to_string(), holder));
+        }
+        LeaseState::Missing => None,
+        LeaseState::Expired(version) => {
+            info!("The lease {} expired. Taking it over", lease.name);
+            Some(version)
+        }
+    };
+    let manifest = json_file(&lease_manifest(
+        &lease,
+        Timestamp::now(),
+        lease_duration(),
+        resource_version.as_deref(),
+    ))?;
+
+    // Creating fails if another process created the lease in the meantime, and replacing
+    // fails if another process took over the expired lease since it was read
+    let command = match resource_version {
+        Some(_) => create_replace_lease(manifest.path()),
+        None => create_create_lease(manifest.path()),
+    };
+    if let Err(err) = command.output() {
+        return match read_lease(&lease)? {
+            LeaseState::Held(holder) => Err(Error::ReleaseLocked(release.to_string(), holder)),
+            _ => Err(err),
+        };
+    }
+    info!("Locked {release} with the lease {}", lease.name);
+
+    Ok(lease)
+}
+
+fn read_lease(lease: &Lease) -> Result<LeaseState> {
+    let existing = create_get_lease(lease).output()?;
+    if existing.trim().is_empty() {
+        return Ok(LeaseState::Missing);
+    }
+
+    Ok(lease_state(
+        &serde_json::from_str(&existing)?,
+        Timestamp::now(),
+    ))
+}
+
+/// Renews the lease a few times per lease duration, so it never expires while the release is changed.
+/// Stops if the lease was taken over, e.g. because renewing failed for too long
+fn renew_lease(lease: Lease) -> Renewal {
+    let (stop, stopped) = mpsc::channel();
+    let interval = (lease_duration() / 3)
+        .try_into()
+        .unwrap_or(RETRY)
+        .max(RETRY);
+
+    let thread = thread::spawn(move || {
+        while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+            match renew(&lease) {
+                Ok(true) => {}
+                Ok(false) => return,
+                Err(err) => warn!("Failed to renew the lease {}: {err}", lease.name),
+            }
+        }
+    });
+
+    Renewal { stop, thread }
+}
+
+/// Bumps the renew time of the lease. False if this process does not hold it anymore
+fn renew(lease: &Lease) -> Result<bool> {
+    let existing = create_get_lease(lease).output()?;
+    let existing: Value = match existing.trim() {
+        "" => Value::Null,
+        existing => serde_json::from_str(existing)?,
+    };
+
+    let holder = existing["spec"]["holderIdentity"].as_str();
+    if holder != Some(lease.holder.as_str()) {
+        warn!(
+            "The lease {} was taken over by {}. Not renewing it anymore",
+            lease.name,
+            holder.unwrap_or("nobody")
+        );
+        return Ok(false);
+    }
+    let Some(version) = existing["metadata"]["resourceVersion"].as_str() else {
+        return Ok(false);
+    };
+    create_patch_lease(lease, &renewal_patch(version, Timestamp::now())).output()?;
+    info!("Renewed the lease {}", lease.name);
+
+    Ok(true)
+}
+
+/// Deletes the lease, unless another process took it over after it expired.
+/// The deletion is conditional on the version that was checked, so a lease taken over meanwhile survives
+fn release_lease(lease: &Lease) -> Result<()> {
+    let existing = create_get_lease(lease).output()?;
+    if existing.trim().is_empty() {
+        return Ok(());
+    }
+    let existing: Value = serde_json::from_str(&existing)?;
+
+    let holder = existing["spec"]["holderIdentity"].as_str();
+    if holder != Some(lease.holder.as_str()) {
+        warn!(
+            "The lease {} was taken over by {}. Leaving it",
+            lease.name,
+            holder.unwrap_or("an unknown holder")
+        );
+        return Ok(());
+    }
+    let options = json!({
+        "apiVersion": "v1",
+        "kind": "DeleteOptions",
+        "preconditions": { "resourceVersion": existing["metadata"]["resourceVersion"] },
+    });
+    create_delete_lease(lease, json_file(&options.to_string())?.path()).output()?;
+
+    Ok(())
+}
+
+/// State of the lease at the given time. A lease expires one duration after it was last renewed
+fn lease_state(lease: &Value, now: Timestamp) -> LeaseState {
+    let spec = &lease["spec"];
+    let expires = spec["renewTime"]
+        .as_str()
+        .or_else(|| spec["acquireTime"].as_str())
+        .and_then(|renewed| renewed.parse::<Timestamp>().ok())
+        .zip(spec["leaseDurationSeconds"].as_i64())
+        .and_then(|(acquired, duration)| {
+            acquired
+                .checked_add(SignedDuration::from_secs(duration))
+                .ok()
+        });
+
+    match (expires, lease["metadata"]["resourceVersion"].as_str()) {
+        (Some(expires), Some(version)) if expires < now => LeaseState::Expired(version.to_string()),
+        _ => LeaseState::Held(
+            spec["holderIdentity"]
+                .as_str()
+                .unwrap_or("an unknown holder")
+                .to_string(),
+        ),
+    }
+}
+
+/// Manifest of the lease held by this process. With a resource version it replaces exactly that version
+fn lease_manifest(
+    lease: &Lease,
+    acquired: Timestamp,
+    duration: SignedDuration,
+    resource_version: Option<&str>,
+) -> String {
+    let mut manifest = json!({
+        "apiVersion": "coordination.k8s.io/v1",
+        "kind": "Lease",
+        "metadata": {
+            "name": lease.name,
+            "namespace": lease.namespace,
+            "labels": { "app.kubernetes.io/managed-by": "shippr" },
+        },
+        "spec": {
+            "holderIdentity": lease.holder,
+            // Kubernetes expects exactly microseconds
+            "acquireTime": format!("{acquired:.6}"),
+            "renewTime": format!("{acquired:.6}"),
+            "leaseDurationSeconds": duration.as_secs(),
+        },
+    });
+    if let Some(version) = resource_version {
+        manifest["metadata"]["resourceVersion"] = json!(version);
+    }
+
+    manifest.to_string()
+}
+
+/// Merge patch of the renew time. Only applies to exactly the version that was checked
+fn renewal_patch(resource_version: &str, renewed: Timestamp) -> String {
+    json!({
+        "metadata": { "resourceVersion": resource_version },
+        "spec": { "renewTime": format!("{renewed:.6}") },
+    })
+    .to_string()
+}
+
+/// kubectl reads manifests and request bodies from files
+fn json_file(content: &str) -> Result<NamedTempFile> {
+    let mut file = tempfile::Builder::new()
+        .prefix("shippr-lease-")
+        .suffix(".json")
+        .tempfile()?;
+    file.write_all(content.as_bytes())?;
+
+    Ok(file)
+}
+
+/// Twice the timeout of helm. The lease is renewed while it is held, so it only expires
+/// if its holder stopped without releasing it
+fn lease_duration() -> SignedDuration {
+    settings::get()
+        .timeout
+        .parse::<SignedDuration>()
+        .ok()
+        .and_then(|timeout| timeout.checked_mul(2))
+        .unwrap_or(DEFAULT_LEASE_DURATION)
+}
+
+/// Who holds a lock taken by this process
+fn holder() -> String {
+    let origin = audit::origin();
+    format!(
+        "{}@{} (pid {}, since {:.0})",
+        origin.user,
+        origin.host,
+        process::id(),
+        Timestamp::now()
+    )
+}
+
+fn create_get_lease(lease: &Lease) -> Command {
+    let mut command = Command::kubectl();
+    command
+        .args(["get", "lease", &lease.name])
+        .args(["--namespace", &lease.namespace])
+        .arg("--ignore-not-found")
+        .args(["-o", "json"]);
+
+    command
+}
+
+fn create_create_lease(manifest: &Path) -> Command {
+    let mut command = Command::kubectl();
+    command.arg("create").arg("-f").arg(manifest);
+
+    command
+}
+
+fn create_replace_lease(manifest: &Path) -> Command {
+    let mut command = Command::kubectl();
+    command.arg("replace").arg("-f").arg(manifest);
+
+    command
+}
+
+fn create_patch_lease(lease: &Lease, patch: &str) -> Command {
+    let mut command = Command::kubectl();
+    command
+        .args(["patch", "lease", &lease.name])
+        .args(["--namespace", &lease.namespace])
+        .args(["--type", "merge"])
+        .args(["-p", patch]);
+
+    command
+}
+
+/// Deletes through the API, as only it takes preconditions
+fn create_delete_lease(lease: &Lease, options: &Path) -> Command {
+    let mut command = Command::kubectl();
+    command
+        .args(["delete", "--raw"])
+        .arg(format!(
+            "/apis/coordination.k8s.io/v1/namespaces/{}/leases/{}",
+            lease.namespace, lease.name
+        ))
+        .arg("-f")
+        .arg(options);
+
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use jiff::{SignedDuration, Timestamp};
+
+    use std::path::Path;
+
+    use super::{
+        Lease, LeaseState, create_delete_lease, create_get_lease, create_patch_lease,
+        lease_manifest, lease_state, lock_file, renewal_patch,
+    };
+    use crate::Error;
+
+    type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+    #[test]
+    fn locked_release_names_holder() -> TestResult {
+        // given
+        let dir = tempfile::tempdir()?;
+        let lock = lock_file(dir.path(), "nginx", "dev")?;
+
+        // when
+        let result = lock_file(dir.path(), "nginx", "dev");
+
+        // then
+        assert!(matches!(
+            result,
+            Err(Error::ReleaseLocked(release, holder)) if release == "nginx" && holder.contains("pid")
+        ));
+        assert!(lock_file(dir.path(), "nginx", "prod").is_ok());
+
+        drop(lock);
+        assert!(lock_file(dir.path(), "nginx", "dev").is_ok());
+
+        Ok(())
+    }
+
+    fn lease() -> Lease {
+        Lease {
+            name: String::from("shippr-dev-nginx"),
+            namespace: String::from("shippr"),
+            holder: String::from("alice@laptop"),
+        }
+    }
+
+    #[test]
+    fn expired_lease_replaced_at_its_version() -> TestResult {
+        // given
+        let acquired: Timestamp = "2026-10-01T12:00:00Z".parse()?;
+        let manifest = serde_json::from_str(&lease_manifest(
+            &lease(),
+            acquired,
+            SignedDuration::from_mins(4),
+            Some("4711"),
+        ))?;
+
+        // when
+        let held = lease_state(&manifest, "2026-10-01T12:03:00Z".parse()?);
+        let expired = lease_state(&manifest, "2026-10-01T12:05:00Z".parse()?);
+
+        // then
+        assert_eq!(
+            manifest["spec"]["acquireTime"],
+            "2026-10-01T12:00:00.000000Z"
+        );
+        assert_eq!(held, LeaseState::Held(String::from("alice@laptop")));
+        assert_eq!(expired, LeaseState::Expired(String::from("4711")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn renewed_lease_not_taken_over() -> TestResult {
+        // given
+        let acquired: Timestamp = "2026-10-01T12:00:00Z".parse()?;
+        let mut manifest: serde_json::Value = serde_json::from_str(&lease_manifest(
+            &lease(),
+            acquired,
+            SignedDuration::from_mins(4),
+            Some("4711"),
+        ))?;
+        let patch: serde_json::Value =
+            serde_json::from_str(&renewal_patch("4711", "2026-10-01T12:03:00Z".parse()?))?;
+        manifest["spec"]["renewTime"] = patch["spec"]["renewTime"].clone();
+
+        // when
+        let renewing = lease_state(&manifest, "2026-10-01T12:05:00Z".parse()?);
+        let stopped = lease_state(&manifest, "2026-10-01T12:08:00Z".parse()?);
+
+        // then
+        assert_eq!(patch["metadata"]["resourceVersion"], "4711");
+        assert_eq!(renewing, LeaseState::Held(String::from("alice@laptop")));
+        assert_eq!(stopped, LeaseState::Expired(String::from("4711")));
+
+        Ok(())
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn patch_lease_with_merge() {
+        // when
+        let result = create_patch_lease(&lease(), "{}");
+
+        // then
+        assert_eq!(result.get_program(), "kubectl");
+        assert_eq!(result.get_args(), [
+            "patch", "lease", "shippr-dev-nginx",
+            "--namespace", "shippr",
+            "--type", "merge",
+            "-p", "{}",
+        ]);
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn delete_lease_with_preconditions() {
+        // when
+        let result = create_delete_lease(&lease(), Path::new("options.json"));
+
+        // then
+        assert_eq!(result.get_program(), "kubectl");
+        assert_eq!(result.get_args(), [
+            "delete", "--raw", "/apis/coordination.k8s.io/v1/namespaces/shippr/leases/shippr-dev-nginx",
+            "-f", "options.json",
+        ]);
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn get_lease_ignores_missing() {
+        // when
+        let result = create_get_lease(&lease());
+
+        // then
+        assert_eq!(result.get_program(), "kubectl");
+        assert_eq!(result.get_args(), [
+            "get", "lease", "shippr-dev-nginx",
+            "--namespace", "shippr",
+            "--ignore-not-found",
+            "-o", "json",
+        ]);
+    }
+}
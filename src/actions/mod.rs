@@ -1,5 +1,5 @@
 mod check;
-pub use check::check;
+pub use check::{CheckOptions, check};
 
 mod cleanup;
 pub use cleanup::action::cleanup_all_namespaces;
@@ -10,12 +10,53 @@ pub use cluster::list::list_clusters;
 pub use cluster::set::set_cluster;
 
 mod deploy;
-pub use deploy::deploy;
+pub use deploy::{DeployOptions, deploy};
+
+mod health;
+pub(crate) use health::HealthCheck;
+
+mod hooks;
+
+mod lock;
+
+mod rollback;
+
+mod template;
+pub use template::template;
+
+mod lint_values;
+pub use lint_values::lint_values;
+
+mod test;
+pub use test::test;
+
+mod status;
+pub use status::status;
 
 mod undeploy;
 pub use undeploy::undeploy;
 
+mod unlock;
+pub use unlock::unlock;
+
 mod init;
-pub use init::initialize_configuration;
+pub use init::{DeploymentFormat, InitOptions, initialize_configuration};
+
+mod validate;
+pub use validate::validate;
+
+mod schema;
+pub use schema::print_schema;
+
+mod import;
+pub use import::import;
+
+mod config;
+pub use config::show_config;
+
+mod audit;
+pub use audit::audit;
 
 mod values;
+
+pub use crate::deploy_config::{ChartLocation, Discovery};
@@ -0,0 +1,240 @@
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{
+    Error, Result,
+    audit::{self, Audited},
+    command::{Command, Execution},
+    output::{self, ReleaseAction, message},
+    settings,
+};
+
+/// State a release is restored to if its deploy fails
+#[derive(Debug, PartialEq)]
+pub(super) enum RestorePoint {
+    /// The last successful revision
+    Revision(u64),
+    /// The release was never deployed successfully
+    Uninstalled,
+}
+
+/// A revision of a release as listed by helm history
+#[derive(Debug, PartialEq, Deserialize)]
+pub(super) struct HistoryEntry {
+    pub(super) revision: u64,
+    pub(super) status: String,
+}
+
+/// Finds the last successful revision of the release. Must be called before the deploy
+pub(super) fn restore_point(name: &str, namespace: &str) -> Result<RestorePoint> {
+    Ok(last_successful(&history(name, namespace)?))
+}
+
+/// Revisions of the release. Empty if the release does not exist
+pub(super) fn history(name: &str, namespace: &str) -> Result<Vec<HistoryEntry>> {
+    let execution = create_history(name, namespace).execute_captured()?;
+    parse_history(name, &execution)
+}
+
+/// Only a missing release has no history. Any other failure, like denied access
+/// or an unreachable cluster, says nothing about the release
+fn parse_history(name: &str, execution: &Execution) -> Result<Vec<HistoryEntry>> {
+    if execution.exit_code == Some(0) {
+        return Ok(serde_json::from_str(&execution.stdout)?);
+    }
+    if execution.stderr.contains("release: not found") {
+        info!("{name} has no history, as it does not exist");
+        return Ok(Vec::new());
+    }
+
+    Err(Error::HistoryUnavailable(
+        name.to_string(),
+        execution.stderr.trim().to_string(),
+    ))
+}
+
+pub(super) fn last_successful(history: &[HistoryEntry]) -> RestorePoint {
+    history
+        .iter()
+        .filter(|entry| entry.status == "deployed" || entry.status == "superseded")
+        .map(|entry| entry.revision)
+        .max()
+        .map_or(RestorePoint::Uninstalled, RestorePoint::Revision)
+}
+
+/// Rolls the release back to the restore point, or uninstalls it if it has none.
+/// Returns what was done. Fails if the rollback or uninstall fails
+pub(super) fn restore(name: &str, namespace: &str, point: RestorePoint) -> Result<String> {
+    let (command, action, outcome) = match point {
+        RestorePoint::Revision(revision) => {
+            message!("Rolling back {name} to revision {revision}");
+            (
+                create_rollback(name, namespace, revision),
+                ReleaseAction::Rollback,
+                format!("Rolled back to revision {revision}"),
+            )
+        }
+        RestorePoint::Uninstalled => {
+            message!("Uninstalling {name}, as it has no successful revision to roll back to");
+            (
+                create_uninstall(name, namespace),
+                ReleaseAction::Undeploy,
+                String::from("Uninstalled, as it had no successful revision"),
+            )
+        }
+    };
+
+    let execution = command.execute()?;
+    let failure = (execution.exit_code != Some(0))
+        .then(|| Error::RestoreFailed(name.to_string(), execution.stderr.trim().to_string()));
+    audit::record(&Audited::new(action, name, namespace), &execution);
+    output::record_executed(name, namespace, action, execution);
+    if let Some(failure) = failure {
+        return Err(failure);
+    }
+    message!("Restored {name}");
+
+    Ok(outcome)
+}
+
+fn create_history(name: &str, namespace: &str) -> Command {
+    let mut command = Command::helm();
+    command
+        .args(["history", name])
+        .args(["--namespace", namespace])
+        .args(["-o", "json"]);
+
+    command
+}
+
+fn create_rollback(name: &str, namespace: &str, revision: u64) -> Command {
+    let mut command = Command::helm();
+    command
+        .release(name)
+        .args(["rollback", name])
+        .arg(revision.to_string())
+        .args(["--namespace", namespace])
+        .arg("--wait")
+        .args(["--timeout", &settings::get().timeout]);
+
+    command
+}
+
+fn create_uninstall(name: &str, namespace: &str) -> Command {
+    let mut command = Command::helm();
+    command
+        .release(name)
+        .args(["uninstall", name])
+        .args(["--namespace", namespace])
+        .arg("--wait");
+
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{
+        HistoryEntry, RestorePoint, create_rollback, create_uninstall, last_successful,
+        parse_history,
+    };
+    use crate::{Error, command::Execution};
+
+    type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+    #[test]
+    fn restore_point_skips_failed_revisions() -> TestResult {
+        // given
+        let history = r#"[
+            {"revision": 1, "status": "superseded", "chart": "nginx-1.0.0"},
+            {"revision": 2, "status": "deployed", "chart": "nginx-1.1.0"},
+            {"revision": 3, "status": "failed", "chart": "nginx-1.2.0"}
+        ]"#;
+
+        // when
+        let result = last_successful(&serde_json::from_str::<Vec<HistoryEntry>>(history)?);
+
+        // then
+        assert_eq!(result, RestorePoint::Revision(2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn uninstalled_without_successful_revision() -> TestResult {
+        // given
+        let history = r#"[{"revision": 1, "status": "failed", "chart": "nginx-1.0.0"}]"#;
+
+        // when
+        let result = last_successful(&serde_json::from_str::<Vec<HistoryEntry>>(history)?);
+
+        // then
+        assert_eq!(result, RestorePoint::Uninstalled);
+
+        Ok(())
+    }
+
+    fn failed_history(stderr: &str) -> Execution {
+        Execution {
+            command: String::from("helm history TestRelease"),
+            exit_code: Some(1),
+            duration: Duration::ZERO,
+            stdout: String::new(),
+            stderr: stderr.to_string(),
+        }
+    }
+
+    #[test]
+    fn missing_release_has_no_history() -> TestResult {
+        // given
+        let execution = failed_history("Error: release: not found\n");
+
+        // when
+        let result = parse_history("TestRelease", &execution)?;
+
+        // then
+        assert!(result.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn cluster_errors_fail_history() {
+        // given
+        let execution = failed_history(
+            "Error: query: failed to query with labels: secrets is forbidden: User \"ci\" cannot list resource \"secrets\"\n",
+        );
+
+        // when
+        let result = parse_history("TestRelease", &execution);
+
+        // then
+        assert!(matches!(
+            result,
+            Err(Error::HistoryUnavailable(name, reason)) if name == "TestRelease" && reason.contains("forbidden")
+        ));
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn rollback_to_revision_or_uninstall() {
+        // when
+        let rollback = create_rollback("TestRelease", "TestNamespace", 2);
+        let uninstall = create_uninstall("TestRelease", "TestNamespace");
+
+        // then
+        assert_eq!(rollback.get_program(), "helm");
+        assert_eq!(rollback.get_args(), [
+            "rollback", "TestRelease", "2",
+            "--namespace", "TestNamespace",
+            "--wait",
+            "--timeout", "2m0s",
+        ]);
+        assert_eq!(uninstall.get_args(), [
+            "uninstall", "TestRelease",
+            "--namespace", "TestNamespace",
+            "--wait",
+        ]);
+    }
+}
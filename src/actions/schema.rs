@@ -0,0 +1,9 @@
+use crate::{Result, deploy_config};
+
+/// Prints the JSON Schema of the deployment file
+pub fn print_schema() -> Result<()> {
+    let schema = serde_json::to_string_pretty(&deploy_config::schema())?;
+    println!("{schema}");
+
+    Ok(())
+}
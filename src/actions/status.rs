@@ -0,0 +1,192 @@
+use std::path::PathBuf;
+
+use serde_json::Value;
+use tracing::{debug, info};
+
+use crate::{
+    Error, Result,
+    command::{Command, Execution},
+    deploy_config::Deployment,
+    output::{self, StatusReport, message},
+    settings::deployment_file_name,
+};
+
+/// Status of a release that does not exist in the cluster
+const NOT_DEPLOYED: &str = "not-deployed";
+
+/// Shows the status of every release defined in the directory
+pub fn status(deploy_file_dir: PathBuf, file: Option<String>) -> Result<()> {
+    debug!(
+        "Received the following parameters: dir: [{:?}], file: [{:?}]",
+        deploy_file_dir, file
+    );
+
+    let file_name = deployment_file_name(file);
+    let deployments = Deployment::all_in(&deploy_file_dir, &file_name)?;
+    info!("Deployment file found. Reading the status of the releases");
+
+    let mut statuses = Vec::new();
+    for deployment in deployments {
+        let name = deployment.release_name();
+        let namespace = &deployment.chart.namespace;
+        let execution = create_status(name, namespace).execute_captured()?;
+        let status = parse_status(name, namespace, &execution)?;
+
+        match status.revision {
+            Some(revision) => message!(
+                "{name} in {namespace}: {}, revision {revision}, chart {}, last deployed {}",
+                status.status,
+                status.chart_version.as_deref().unwrap_or("unknown"),
+                status.last_deployed.as_deref().unwrap_or("unknown"),
+            ),
+            None => message!("{name} in {namespace}: {}", status.status),
+        }
+        statuses.push(status);
+    }
+    output::record_statuses(statuses);
+
+    Ok(())
+}
+
+/// A missing release is not deployed. Any other failure, like denied access
+/// or an unreachable cluster, says nothing about the release
+fn parse_status(name: &str, namespace: &str, execution: &Execution) -> Result<StatusReport> {
+    let mut status = StatusReport {
+        name: name.to_string(),
+        namespace: namespace.to_string(),
+        status: String::from(NOT_DEPLOYED),
+        revision: None,
+        chart_version: None,
+        app_version: None,
+        last_deployed: None,
+    };
+
+    if execution.exit_code != Some(0) {
+        if execution.stderr.contains("release: not found") {
+            return Ok(status);
+        }
+        return Err(Error::StatusUnavailable(
+            name.to_string(),
+            execution.stderr.trim().to_string(),
+        ));
+    }
+
+    let release: Value = serde_json::from_str(&execution.stdout)?;
+    let text = |value: &Value| value.as_str().map(str::to_string);
+    let info = &release["info"];
+    let metadata = &release["chart"]["metadata"];
+    status.status = text(&info["status"]).unwrap_or_else(|| String::from("unknown"));
+    status.revision = release["version"].as_u64();
+    status.chart_version = text(&metadata["version"]);
+    status.app_version = text(&metadata["appVersion"]);
+    status.last_deployed = text(&info["last_deployed"]);
+
+    Ok(status)
+}
+
+fn create_status(name: &str, namespace: &str) -> Command {
+    let mut command = Command::helm();
+    command
+        .args(["status", name])
+        .args(["--namespace", namespace])
+        .args(["-o", "json"]);
+
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{create_status, parse_status};
+    use crate::{Error, command::Execution};
+
+    type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+    fn status(exit_code: i32, stdout: &str, stderr: &str) -> Execution {
+        Execution {
+            command: String::from("helm status TestRelease"),
+            exit_code: Some(exit_code),
+            duration: Duration::ZERO,
+            stdout: stdout.to_string(),
+            stderr: stderr.to_string(),
+        }
+    }
+
+    #[test]
+    fn status_of_deployed_release() -> TestResult {
+        // given
+        let execution = status(
+            0,
+            r#"{
+                "name": "TestRelease",
+                "info": {"status": "deployed", "last_deployed": "2026-10-01T12:00:00.000000+02:00"},
+                "chart": {"metadata": {"name": "nginx", "version": "1.2.3", "appVersion": "1.27.0"}},
+                "version": 4,
+                "namespace": "TestNamespace"
+            }"#,
+            "",
+        );
+
+        // when
+        let result = parse_status("TestRelease", "TestNamespace", &execution)?;
+
+        // then
+        assert_eq!(result.status, "deployed");
+        assert_eq!(result.revision, Some(4));
+        assert_eq!(result.chart_version.as_deref(), Some("1.2.3"));
+        assert_eq!(result.app_version.as_deref(), Some("1.27.0"));
+        assert_eq!(
+            result.last_deployed.as_deref(),
+            Some("2026-10-01T12:00:00.000000+02:00")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn missing_release_not_deployed() -> TestResult {
+        // given
+        let execution = status(1, "", "Error: release: not found\n");
+
+        // when
+        let result = parse_status("TestRelease", "TestNamespace", &execution)?;
+
+        // then
+        assert_eq!(result.status, "not-deployed");
+        assert_eq!(result.revision, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn cluster_errors_fail_status() {
+        // given
+        let execution = status(1, "", "Error: Kubernetes cluster unreachable\n");
+
+        // when
+        let result = parse_status("TestRelease", "TestNamespace", &execution);
+
+        // then
+        assert!(matches!(
+            result,
+            Err(Error::StatusUnavailable(name, cause))
+                if name == "TestRelease" && cause == "Error: Kubernetes cluster unreachable"
+        ));
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn status_as_json() {
+        // when
+        let result = create_status("TestRelease", "TestNamespace");
+
+        // then
+        assert_eq!(result.get_program(), "helm");
+        assert_eq!(result.get_args(), [
+            "status", "TestRelease",
+            "--namespace", "TestNamespace",
+            "-o", "json",
+        ]);
+    }
+}
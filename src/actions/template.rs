@@ -0,0 +1,288 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use tracing::{debug, info};
+use yaml_rust2::YamlLoader;
+
+use super::values;
+
+/// First line of every file written by template. Marks the files that are replaced on the next render
+const GENERATED_HEADER: &str = "# Rendered by shippr template. Changes are overwritten\n";
+use crate::{
+    Error, Result,
+    command::Command,
+    deploy_config::*,
+    output::{self, message},
+    settings::{self, deployment_file_name},
+};
+
+/// Renders the manifests of every release defined in the directory.
+/// Without an output directory they are printed as a single stream
+pub fn template(
+    profile: Option<String>,
+    deploy_file_dir: PathBuf,
+    file: Option<String>,
+    out: Option<PathBuf>,
+) -> Result<()> {
+    debug!(
+        "Received the following parameters: profile: [{profile:?}], dir: [{deploy_file_dir:?}], file: [{file:?}], out: [{out:?}]"
+    );
+
+    let file_name = deployment_file_name(file);
+    let deployments = Deployment::all_in(&deploy_file_dir, &file_name)?;
+    let grouped = deployments.len() > 1;
+
+    for deployment in deployments {
+        let values_default = values::default(deploy_file_dir.clone(), deployment.variant())?;
+        let values_profile =
+            values::profile(deploy_file_dir.clone(), &profile, deployment.variant())?;
+
+        let manifest = render(
+            &deployment,
+            values_default.path(),
+            values_profile.as_ref().map(|p| p.path()),
+        )?;
+
+        let Some(out) = &out else {
+            output::passthrough(manifest.as_bytes())?;
+            continue;
+        };
+        // Releases of one directory get their own directory, so their objects cannot collide
+        let dir = if grouped {
+            out.join(deployment.release_name())
+        } else {
+            out.clone()
+        };
+        let files = write_objects(&dir, &manifest)?;
+        message!(
+            "Rendered {files} files of {} into {}",
+            deployment.release_name(),
+            dir.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Renders the manifests of the release locally, with the values and chart a deploy would use
+pub(super) fn render(
+    deployment: &Deployment,
+    values_default: &Path,
+    values_profile: Option<&Path>,
+) -> Result<String> {
+    info!("Rendering {}", deployment.release_name());
+    create_template(deployment, values_default, values_profile).output()
+}
+
+/// Writes every object of the manifest into `<kind>-<name>.yaml`. Returns how many files were written.
+/// Files of a previous render are removed first, so objects removed from the chart do not linger
+fn write_objects(dir: &Path, manifest: &str) -> Result<usize> {
+    let files = split_objects(manifest)?;
+    fs::create_dir_all(dir)?;
+    remove_generated(dir)?;
+    for (file_name, content) in &files {
+        fs::write(dir.join(file_name), format!("{GENERATED_HEADER}{content}"))?;
+    }
+    info!("Wrote {} files into {dir:?}", files.len());
+
+    Ok(files.len())
+}
+
+/// Removes the YAML files template wrote into the directory. Any other file is kept
+fn remove_generated(dir: &Path) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|extension| extension != "yaml") || !path.is_file() {
+            continue;
+        }
+        if fs::read_to_string(&path).is_ok_and(|content| content.starts_with(GENERATED_HEADER)) {
+            debug!("Removing the previously rendered {path:?}");
+            fs::remove_file(&path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Splits the manifest into its objects, by file name.
+/// Objects with the same kind and name, e.g. in different namespaces, share a file
+fn split_objects(manifest: &str) -> Result<BTreeMap<String, String>> {
+    let mut files: BTreeMap<String, String> = BTreeMap::new();
+    for document in documents(manifest) {
+        let parsed = YamlLoader::load_from_str(&document)
+            .map_err(|err| Error::YamlError(err.to_string()))?;
+        let Some(object) = parsed.first() else {
+            continue;
+        };
+        let (Some(kind), Some(name)) =
+            (object["kind"].as_str(), object["metadata"]["name"].as_str())
+        else {
+            continue;
+        };
+
+        let content = files
+            .entry(format!("{}-{name}.yaml", kind.to_lowercase()))
+            .or_default();
+        if !content.is_empty() {
+            content.push_str("---\n");
+        }
+        content.push_str(&document);
+    }
+
+    Ok(files)
+}
+
+/// Documents of a YAML stream as written, so comments like `# Source:` are kept
+fn documents(manifest: &str) -> Vec<String> {
+    let mut documents = vec![String::new()];
+    for line in manifest.lines() {
+        if line.trim_end() == "---" {
+            documents.push(String::new());
+        } else if let Some(document) = documents.last_mut() {
+            document.push_str(line);
+            document.push('\n');
+        }
+    }
+
+    documents
+        .into_iter()
+        .filter(|document| !document.trim().is_empty())
+        .collect()
+}
+
+pub(super) fn create_template(
+    deployment: &Deployment,
+    values_default: &Path,
+    values_profile: Option<&Path>,
+) -> Command {
+    let mut command = Command::helm();
+    command
+        .release(deployment.release_name())
+        .arg("template")
+        .args(["-f", values_default.to_str().unwrap()]);
+
+    if let Some(p) = values_profile {
+        command.args(["-f", p.to_str().unwrap()]);
+    }
+    if let Some(version) = &settings::get().kube_version {
+        command.args(["--kube-version", version]);
+    }
+    deployment.append_deployment_information(&mut command);
+
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use crate::deploy_config::test_fixtures::deployment;
+
+    use super::{create_template, split_objects, write_objects};
+
+    type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+    const MANIFEST: &str = "---
+# Source: nginx/templates/service.yaml
+apiVersion: v1
+kind: Service
+metadata:
+  name: nginx
+---
+# Source: nginx/templates/deployment.yaml
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: nginx
+spec:
+  replicas: 1
+---
+# Source: nginx/templates/empty.yaml
+";
+
+    #[test]
+    fn one_file_per_object() -> TestResult {
+        // when
+        let result = split_objects(MANIFEST)?;
+
+        // then
+        assert_eq!(
+            result.keys().collect::<Vec<_>>(),
+            ["deployment-nginx.yaml", "service-nginx.yaml"]
+        );
+        assert_eq!(
+            result["service-nginx.yaml"],
+            "# Source: nginx/templates/service.yaml\napiVersion: v1\nkind: Service\nmetadata:\n  name: nginx\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn objects_written_into_directory() -> TestResult {
+        // given
+        let dir = tempfile::tempdir()?;
+        let out = dir.path().join("rendered");
+
+        // when
+        let result = write_objects(&out, MANIFEST)?;
+
+        // then
+        assert_eq!(result, 2);
+        assert!(fs::read_to_string(out.join("deployment-nginx.yaml"))?.contains("replicas: 1"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn removed_objects_not_kept() -> TestResult {
+        // given
+        let dir = tempfile::tempdir()?;
+        write_objects(dir.path(), MANIFEST)?;
+        fs::write(dir.path().join("kustomization.yaml"), "resources: []\n")?;
+        let manifest = MANIFEST
+            .split("---\n# Source: nginx/templates/deployment.yaml")
+            .next();
+
+        // when
+        let result = write_objects(dir.path(), manifest.unwrap_or_default())?;
+
+        // then
+        assert_eq!(result, 1);
+        assert!(dir.path().join("service-nginx.yaml").exists());
+        assert!(!dir.path().join("deployment-nginx.yaml").exists());
+        assert!(dir.path().join("kustomization.yaml").exists());
+
+        Ok(())
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn template_with_profile() {
+        // given
+        let deployment = deployment();
+
+        // when
+        let result = create_template(
+            &deployment,
+            Path::new("values-default.yaml"),
+            Some(Path::new("values-test.yaml")),
+        );
+
+        // then
+        assert_eq!(result.get_program(), "helm");
+        assert_eq!(result.get_args(), [
+            "template",
+            "-f", "values-default.yaml",
+            "-f", "values-test.yaml",
+            "--version", "TestVersion",
+            "--namespace", "TestNamespace",
+            "--create-namespace",
+            "TestRelease", "TestChartName",
+            "--repo", "TestRepo",
+        ]);
+    }
+}
@@ -0,0 +1,80 @@
+use std::path::PathBuf;
+
+use tracing::{debug, info};
+
+use crate::{
+    Error, Result,
+    command::Command,
+    deploy_config::Deployment,
+    output::{self, ReleaseAction, message},
+    settings::{self, deployment_file_name},
+};
+
+/// Runs the tests of every release defined in the directory
+pub fn test(deploy_file_dir: PathBuf, file: Option<String>) -> Result<()> {
+    debug!(
+        "Received the following parameters: dir: [{:?}], file: [{:?}]",
+        deploy_file_dir, file
+    );
+
+    let file_name = deployment_file_name(file);
+    let deployments = Deployment::all_in(&deploy_file_dir, &file_name)?;
+    info!("Deployment file found. Testing releases");
+
+    let mut failed = Vec::new();
+    for deployment in deployments {
+        let name = deployment.release_name();
+        if !run_tests(name, &deployment.chart.namespace)? {
+            failed.push(name.to_string());
+        }
+    }
+    if !failed.is_empty() {
+        return Err(Error::TestsFailed(failed));
+    }
+
+    Ok(())
+}
+
+/// Runs the tests of the release and prints the logs of the test pods.
+/// Returns whether all tests passed
+pub(super) fn run_tests(name: &str, namespace: &str) -> Result<bool> {
+    message!("Testing {name}");
+    let execution = create_test(name, namespace).execute()?;
+    let passed = execution.exit_code == Some(0);
+    output::record_executed(name, namespace, ReleaseAction::Test, execution);
+
+    Ok(passed)
+}
+
+fn create_test(name: &str, namespace: &str) -> Command {
+    let mut command = Command::helm();
+    command
+        .release(name)
+        .args(["test", name])
+        .args(["--namespace", namespace])
+        .arg("--logs")
+        .args(["--timeout", &settings::get().timeout]);
+
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::create_test;
+
+    #[rustfmt::skip]
+    #[test]
+    fn test_with_logs() {
+        // when
+        let result = create_test("TestRelease", "TestNamespace");
+
+        // then
+        assert_eq!(result.get_program(), "helm");
+        assert_eq!(result.get_args(), [
+            "test", "TestRelease",
+            "--namespace", "TestNamespace",
+            "--logs",
+            "--timeout", "2m0s",
+        ]);
+    }
+}
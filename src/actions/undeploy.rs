@@ -1,32 +1,122 @@
-use std::path::PathBuf;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use tracing::{debug, info};
 
-use crate::{Result, command::Command, deploy_config::Deployment, io::user_confirmation};
-
-pub fn undeploy(deploy_file_dir: PathBuf, no_verify: bool) -> Result<()> {
+use super::{
+    cleanup::action::is_released,
+    hooks::{self, HookContext},
+    lock,
+};
+use crate::{
+    Error, Result,
+    audit::{self, Audited},
+    command::Command,
+    deploy_config::{Deployment, HookStage},
+    io::user_confirmation,
+    output::{self, ReleaseAction, message},
+    settings::{self, deployment_file_name},
+};
+
+/// Undeploys every release defined in the directory.
+/// Returns the amount of releases that were undeployed, or would be undeployed on a dry-run
+pub fn undeploy(
+    deploy_file_dir: PathBuf,
+    file: Option<String>,
+    no_verify: bool,
+    dry_run: bool,
+    wait_for_lock: Option<Duration>,
+) -> Result<usize> {
     debug!(
-        "Received the following parameters: dir: [{:?}], no-verify: [{:?}]",
-        deploy_file_dir, no_verify
+        "Received the following parameters: dir: [{:?}], file: [{:?}], no-verify: [{:?}], dry-run: [{:?}], wait-for-lock: [{:?}]",
+        deploy_file_dir, file, no_verify, dry_run, wait_for_lock
     );
 
-    let deployment = Deployment::new(&deploy_file_dir, None)?;
+    let file_name = deployment_file_name(file);
+    let deployments = Deployment::all_in(&deploy_file_dir, &file_name)?;
     info!("Deployment file found. Checking deployment");
 
-    let prompt = String::from("Do you really want to undeploy? [Y/N]");
+    let mut undeployed = 0;
+    for deployment in deployments {
+        undeployed += undeploy_release(
+            deployment,
+            &deploy_file_dir,
+            no_verify,
+            dry_run,
+            wait_for_lock,
+        )?;
+    }
+
+    Ok(undeployed)
+}
+
+fn undeploy_release(
+    deployment: Deployment,
+    dir: &Path,
+    no_verify: bool,
+    dry_run: bool,
+    wait_for_lock: Option<Duration>,
+) -> Result<usize> {
+    let name = deployment.release_name().to_string();
+    let namespace = deployment.chart.namespace.clone();
+    if settings::get().protected_namespaces.contains(&namespace) {
+        return Err(Error::ProtectedNamespace(namespace));
+    }
+
+    if dry_run {
+        if !is_released(&name, &namespace)? {
+            message!("Nothing to undeploy for {name}");
+            return Ok(0);
+        }
+
+        let command = create_undeploy(&deployment);
+        message!("The following would be run:");
+        message!("{command}");
+        output::record_planned(&name, &namespace, ReleaseAction::PlannedUndeploy, &command);
+        return Ok(1);
+    }
+
+    let prompt = format!("Do you really want to undeploy {name}? [Y/N]");
     if !no_verify && !user_confirmation(&prompt)? {
-        return Ok(());
+        return Ok(0);
     }
 
-    println!("Undeploying chart..");
-    create_undeploy(deployment).execute()?;
+    let _lock = lock::acquire(&name, &namespace, wait_for_lock)?;
+    let context = HookContext::new(&deployment, None, dir);
+    hooks::run(
+        HookStage::PreUndeploy,
+        deployment.chart.hooks(HookStage::PreUndeploy),
+        &context,
+    )?;
+
+    message!("Undeploying {name}..");
+    let execution = create_undeploy(&deployment).execute()?;
+    let succeeded = execution.exit_code == Some(0);
+    audit::record(
+        &Audited {
+            chart_version: deployment.chart.version(),
+            ..Audited::new(ReleaseAction::Undeploy, &name, &namespace)
+        },
+        &execution,
+    );
+    output::record_executed(&name, &namespace, ReleaseAction::Undeploy, execution);
+
+    if succeeded {
+        hooks::run(
+            HookStage::PostUndeploy,
+            deployment.chart.hooks(HookStage::PostUndeploy),
+            &context,
+        )?;
+    }
 
-    Ok(())
+    Ok(1)
 }
 
-fn create_undeploy(deployment: Deployment) -> Command {
-    let mut command = Command::new("helm");
-    command.arg("uninstall");
+fn create_undeploy(deployment: &Deployment) -> Command {
+    let mut command = Command::helm();
+    command.release(deployment.release_name()).arg("uninstall");
 
     deployment.append_undeployment_information(&mut command);
 
@@ -50,7 +140,7 @@ mod tests {
         let deployment = deployment();
 
         // when
-        let result = create_undeploy(deployment);
+        let result = create_undeploy(&deployment);
 
         // then
         assert_eq!(result.get_program(), "helm");
@@ -0,0 +1,176 @@
+use std::path::PathBuf;
+
+use tracing::{debug, info};
+
+use super::{
+    lock,
+    rollback::{self, HistoryEntry, RestorePoint},
+};
+use crate::{
+    Error, Result,
+    audit::{self, Audited},
+    command::Command,
+    deploy_config::Deployment,
+    io::user_confirmation,
+    output::{self, ReleaseAction, message},
+    settings::deployment_file_name,
+};
+
+/// States helm leaves a release in if it is interrupted.
+/// Helm refuses to change a release in one of them
+const PENDING_STATES: [&str; 3] = ["pending-install", "pending-upgrade", "pending-rollback"];
+
+/// How a stuck release is recovered
+#[derive(Debug, PartialEq)]
+enum Recovery {
+    /// Roll back to the last successful revision
+    Rollback(u64),
+    /// There is no successful revision. Only the pending revision is removed,
+    /// so the next deploy can adopt the resources of the release
+    RemoveRevision(u64),
+}
+
+/// Recovers every release of the directory that is stuck in a pending state
+pub fn unlock(deploy_file_dir: PathBuf, file: Option<String>, no_verify: bool) -> Result<()> {
+    debug!(
+        "Received the following parameters: dir: [{deploy_file_dir:?}], file: [{file:?}], no-verify: [{no_verify:?}]"
+    );
+
+    let file_name = deployment_file_name(file);
+    for deployment in Deployment::all_in(&deploy_file_dir, &file_name)? {
+        let name = deployment.release_name();
+        let namespace = &deployment.chart.namespace;
+
+        let _lock = lock::acquire(name, namespace, None)?;
+        if !recover(name, namespace, no_verify)? {
+            message!("{name} is not stuck");
+        }
+    }
+
+    Ok(())
+}
+
+/// Recovers the release if it is stuck in a pending state. Returns whether it was stuck
+pub(super) fn recover(name: &str, namespace: &str, no_verify: bool) -> Result<bool> {
+    let history = rollback::history(name, namespace)?;
+    let Some((status, recovery)) = plan(&history) else {
+        info!("{name} is not in a pending state");
+        return Ok(false);
+    };
+
+    let prompt = match recovery {
+        Recovery::Rollback(revision) => format!(
+            "{name} is stuck in {status}. Do you want to roll it back to revision {revision}? [Y/N]"
+        ),
+        Recovery::RemoveRevision(revision) => format!(
+            "{name} is stuck in {status} and has no successful revision. Do you want to remove its pending revision {revision}? [Y/N]"
+        ),
+    };
+    if !no_verify && !user_confirmation(&prompt)? {
+        message!("{name} stays in {status}");
+        return Ok(true);
+    }
+
+    match recovery {
+        Recovery::Rollback(revision) => {
+            rollback::restore(name, namespace, RestorePoint::Revision(revision))?;
+        }
+        Recovery::RemoveRevision(revision) => {
+            message!("Removing the pending revision {revision} of {name}");
+            let execution = create_delete_revision(name, namespace, revision).execute()?;
+            let failure = (execution.exit_code != Some(0)).then(|| {
+                Error::RestoreFailed(name.to_string(), execution.stderr.trim().to_string())
+            });
+            audit::record(
+                &Audited::new(ReleaseAction::Unlock, name, namespace),
+                &execution,
+            );
+            output::record_executed(name, namespace, ReleaseAction::Unlock, execution);
+            if let Some(failure) = failure {
+                return Err(failure);
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+/// The pending state of the latest revision and how to recover from it
+fn plan(history: &[HistoryEntry]) -> Option<(&str, Recovery)> {
+    let latest = history.iter().max_by_key(|entry| entry.revision)?;
+    if !PENDING_STATES.contains(&latest.status.as_str()) {
+        return None;
+    }
+
+    let recovery = match rollback::last_successful(history) {
+        RestorePoint::Revision(revision) => Recovery::Rollback(revision),
+        RestorePoint::Uninstalled => Recovery::RemoveRevision(latest.revision),
+    };
+
+    Some((&latest.status, recovery))
+}
+
+/// Helm stores every revision of a release in a secret
+fn create_delete_revision(name: &str, namespace: &str, revision: u64) -> Command {
+    let mut command = Command::kubectl();
+    command
+        .release(name)
+        .args(["delete", "secret"])
+        .arg(format!("sh.helm.release.v1.{name}.v{revision}"))
+        .args(["--namespace", namespace]);
+
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::{Recovery, create_delete_revision, plan};
+    use crate::actions::rollback::HistoryEntry;
+
+    fn history(statuses: &[&str]) -> Vec<HistoryEntry> {
+        statuses
+            .iter()
+            .zip(1..)
+            .map(|(status, revision)| HistoryEntry {
+                revision,
+                status: status.to_string(),
+            })
+            .collect()
+    }
+
+    #[rstest]
+    #[case(&["superseded", "deployed", "pending-upgrade"], Some(("pending-upgrade", Recovery::Rollback(2))))]
+    #[case(&["deployed", "failed", "pending-rollback"], Some(("pending-rollback", Recovery::Rollback(1))))]
+    #[case(&["pending-install"], Some(("pending-install", Recovery::RemoveRevision(1))))]
+    #[case(&["superseded", "deployed"], None)]
+    #[case(&[], None)]
+    fn recovery_of_pending_release(
+        #[case] statuses: &[&str],
+        #[case] expected: Option<(&str, Recovery)>,
+    ) {
+        // given
+        let history = history(statuses);
+
+        // when
+        let result = plan(&history);
+
+        // then
+        assert_eq!(result, expected);
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn delete_secret_of_revision() {
+        // when
+        let result = create_delete_revision("TestRelease", "TestNamespace", 3);
+
+        // then
+        assert_eq!(result.get_program(), "kubectl");
+        assert_eq!(result.get_args(), [
+            "delete", "secret", "sh.helm.release.v1.TestRelease.v3",
+            "--namespace", "TestNamespace",
+        ]);
+    }
+}
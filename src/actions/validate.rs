@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+
+use tracing::debug;
+
+use crate::{
+    Result,
+    deploy_config::{Deployment, Discovery},
+    output::message,
+    settings::deployment_file_name,
+};
+
+/// Validates the deployment file in the directory.
+/// If the directory has no deployment file, every deployment file below it is validated
+pub fn validate(dir: PathBuf, depth: usize) -> Result<()> {
+    debug!("Received the following parameters: dir: [{dir:?}], depth: [{depth:?}]");
+
+    let file_name = deployment_file_name(None);
+    let deployments = if file_name.exists_in(&dir) {
+        Deployment::all_in(&dir, &file_name)?
+    } else {
+        Deployment::discover(
+            &dir,
+            &file_name,
+            &Discovery {
+                depth,
+                strict: true,
+            },
+        )?
+    };
+
+    if deployments.is_empty() {
+        message!("No deployment files found");
+        return Ok(());
+    }
+
+    for deployment in deployments {
+        message!("{} is valid", deployment.release_name());
+    }
+
+    Ok(())
+}
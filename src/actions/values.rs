@@ -1,29 +1,278 @@
-use crate::{Error, Result};
-use std::path::PathBuf;
+use crate::{Error, Result, yaml};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+use tempfile::NamedTempFile;
 use tracing::{debug, info};
 
-pub fn default(base_path: PathBuf) -> Result<PathBuf> {
-    debug!("Checking values-default.yaml exists");
-    let values_default = base_path.join("values-default.yaml");
-    if !values_default.exists() {
-        return Err(Error::ValuesDefaultMissing(values_default));
+/// Supported extensions of values files. Only YAML is understood by helm, the others are converted
+const EXTENSIONS: [&str; 4] = ["yaml", "yml", "json", "toml"];
+
+/// A values file that can be passed to helm.
+/// Values files that are not YAML are converted into a temporary file, which is removed on drop
+#[derive(Debug)]
+pub struct ValuesFile {
+    path: PathBuf,
+    /// The file the values were read from
+    source: PathBuf,
+    _converted: Option<NamedTempFile>,
+}
+
+impl ValuesFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn source(&self) -> &Path {
+        &self.source
     }
+}
 
-    Ok(values_default)
+/// Values files of a variant of the deployment file are named `values-<profile>.<variant>`
+fn file_stem(name: &str, variant: Option<&str>) -> String {
+    match variant {
+        Some(variant) => format!("values-{name}.{variant}"),
+        None => format!("values-{name}"),
+    }
 }
 
-pub fn profile(base_path: PathBuf, profile: &Option<String>) -> Result<Option<PathBuf>> {
+pub fn default(base_path: PathBuf, variant: Option<&str>) -> Result<ValuesFile> {
+    let file_name = file_stem("default", variant);
+    debug!("Checking {file_name} exists");
+    match find(&base_path, &file_name)? {
+        Some(values_default) => convert(values_default),
+        None => Err(Error::ValuesDefaultMissing(
+            base_path.join(format!("{file_name}.yaml")),
+        )),
+    }
+}
+
+pub fn profile(
+    base_path: PathBuf,
+    profile: &Option<String>,
+    variant: Option<&str>,
+) -> Result<Option<ValuesFile>> {
     debug!("Checking if profile is set");
     if let Some(p) = profile {
-        let file_name = format!("values-{p}.yaml");
+        let file_name = file_stem(p, variant);
         info!("Profile is set. Checking {} exists", file_name);
-        let values_profile = base_path.join(file_name);
-        if !values_profile.exists() {
-            return Err(Error::ValuesProfileMissing(values_profile));
+        match find(&base_path, &file_name)? {
+            Some(values_profile) => convert(values_profile).map(Some),
+            None => Err(Error::ValuesProfileMissing(
+                base_path.join(format!("{file_name}.yaml")),
+            )),
         }
-
-        Ok(Some(values_profile))
     } else {
         Ok(None)
     }
 }
+
+/// Profiles with a values file in the directory, sorted by name
+pub(super) fn profiles(base_path: &Path, variant: Option<&str>) -> Result<Vec<String>> {
+    let mut profiles = Vec::new();
+    for entry in fs::read_dir(base_path)? {
+        let path = entry?.path();
+        let (Some(stem), Some(extension)) = (
+            path.file_stem().and_then(|stem| stem.to_str()),
+            path.extension().and_then(|extension| extension.to_str()),
+        ) else {
+            continue;
+        };
+        if !EXTENSIONS.contains(&extension) {
+            continue;
+        }
+
+        let Some(name) = stem.strip_prefix("values-") else {
+            continue;
+        };
+        let profile = match variant {
+            Some(variant) => name.strip_suffix(&format!(".{variant}")),
+            None => Some(name),
+        };
+        if let Some(profile) = profile.filter(|p| *p != "default" && !p.contains('.')) {
+            profiles.push(profile.to_string());
+        }
+    }
+    profiles.sort();
+    profiles.dedup();
+
+    Ok(profiles)
+}
+
+/// Finds the values file with any of the supported extensions. Errors if more than one exists
+fn find(base_path: &Path, file_stem: &str) -> Result<Option<PathBuf>> {
+    let mut found: Vec<PathBuf> = EXTENSIONS
+        .iter()
+        .map(|extension| base_path.join(format!("{file_stem}.{extension}")))
+        .filter(|path| path.exists())
+        .collect();
+
+    if found.len() > 1 {
+        return Err(Error::AmbiguousValuesFiles(
+            found
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect(),
+        ));
+    }
+
+    Ok(found.pop())
+}
+
+fn convert(path: PathBuf) -> Result<ValuesFile> {
+    let content = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => serde_json::from_str(&fs::read_to_string(&path)?)?,
+        Some("toml") => toml::from_str(&fs::read_to_string(&path)?)
+            .map_err(|err| Error::TomlError(format!("{}: {err}", path.display())))?,
+        _ => {
+            return Ok(ValuesFile {
+                source: path.clone(),
+                path,
+                _converted: None,
+            });
+        }
+    };
+
+    let mut converted = tempfile::Builder::new()
+        .prefix("shippr-values-")
+        .suffix(".yaml")
+        .tempfile()?;
+    converted.write_all(yaml::to_yaml(&content)?.as_bytes())?;
+    info!("Converted {path:?} into {:?}", converted.path());
+
+    Ok(ValuesFile {
+        path: converted.path().to_path_buf(),
+        source: path,
+        _converted: Some(converted),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{default, profile, profiles};
+
+    type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+    #[test]
+    fn yaml_used_as_is() -> TestResult {
+        // given
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("values-default.yml"), "replicas: 1\n")?;
+
+        // when
+        let result = default(dir.path().to_path_buf(), None)?;
+
+        // then
+        assert_eq!(result.path(), dir.path().join("values-default.yml"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn json_and_toml_converted_to_yaml() -> TestResult {
+        // given
+        let dir = tempfile::tempdir()?;
+        fs::write(
+            dir.path().join("values-default.json"),
+            r#"{"image": {"tag": "1.25"}, "replicas": 2}"#,
+        )?;
+        fs::write(dir.path().join("values-prod.toml"), "replicas = 3\n")?;
+
+        // when
+        let values_default = default(dir.path().to_path_buf(), None)?;
+        let values_profile = profile(dir.path().to_path_buf(), &Some(String::from("prod")), None)?
+            .ok_or("profile missing")?;
+
+        // then
+        assert_eq!(
+            fs::read_to_string(values_default.path())?,
+            "image:\n  tag: \"1.25\"\nreplicas: 2\n"
+        );
+        assert_eq!(fs::read_to_string(values_profile.path())?, "replicas: 3\n");
+
+        let converted = values_default.path().to_path_buf();
+        drop(values_default);
+        assert!(!converted.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn variant_uses_own_values() -> TestResult {
+        // given
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("values-default.yaml"), "")?;
+        fs::write(dir.path().join("values-default.api.yaml"), "")?;
+        fs::write(dir.path().join("values-prod.api.yaml"), "")?;
+
+        // when
+        let values_default = default(dir.path().to_path_buf(), Some("api"))?;
+        let values_profile = profile(
+            dir.path().to_path_buf(),
+            &Some(String::from("prod")),
+            Some("api"),
+        )?
+        .ok_or("profile missing")?;
+
+        // then
+        assert_eq!(
+            values_default.path(),
+            dir.path().join("values-default.api.yaml")
+        );
+        assert_eq!(
+            values_profile.path(),
+            dir.path().join("values-prod.api.yaml")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn profiles_of_variant() -> TestResult {
+        // given
+        let dir = tempfile::tempdir()?;
+        for file in [
+            "values-default.yaml",
+            "values-prod.yaml",
+            "values-dev.toml",
+            "values-default.api.yaml",
+            "values-prod.api.yml",
+            "values-notes.txt",
+        ] {
+            fs::write(dir.path().join(file), "")?;
+        }
+
+        // when
+        let result = profiles(dir.path(), None)?;
+        let variant = profiles(dir.path(), Some("api"))?;
+
+        // then
+        assert_eq!(result, ["dev", "prod"]);
+        assert_eq!(variant, ["prod"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn multiple_formats_err() -> TestResult {
+        // given
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("values-default.yaml"), "")?;
+        fs::write(dir.path().join("values-default.json"), "{}")?;
+
+        // when
+        let result = default(dir.path().to_path_buf(), None);
+
+        // then
+        assert!(matches!(
+            result.err(),
+            Some(crate::Error::AmbiguousValuesFiles(files)) if files.len() == 2
+        ));
+
+        Ok(())
+    }
+}
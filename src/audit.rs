@@ -0,0 +1,385 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use jiff::{Timestamp, Zoned, civil::Date, tz::TimeZone};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, info, warn};
+
+use crate::{
+    Error, Result,
+    command::{Command, Execution},
+    output::{ReleaseAction, message},
+    settings,
+};
+
+/// Context of the process, which is the same for every record
+static ORIGIN: OnceLock<Origin> = OnceLock::new();
+
+/// A change shippr made to a release
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub(crate) struct AuditRecord {
+    pub(crate) timestamp: Timestamp,
+    pub(crate) user: String,
+    pub(crate) host: String,
+    /// kubectl context the command ran against
+    pub(crate) context: Option<String>,
+    /// Command line shippr was called with
+    pub(crate) command: String,
+    pub(crate) action: ReleaseAction,
+    pub(crate) release: String,
+    pub(crate) namespace: String,
+    pub(crate) profile: Option<String>,
+    pub(crate) chart_version: Option<String>,
+    /// SHA-256 of every values file, by path
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) values: BTreeMap<String, String>,
+    pub(crate) success: bool,
+    pub(crate) exit_code: Option<i32>,
+    pub(crate) duration_ms: u128,
+}
+
+#[derive(Debug)]
+pub(crate) struct Origin {
+    pub(crate) user: String,
+    pub(crate) host: String,
+    pub(crate) context: Option<String>,
+    pub(crate) command: String,
+}
+
+/// The release an executed command changed
+#[derive(Debug)]
+pub(crate) struct Audited<'a> {
+    pub(crate) action: ReleaseAction,
+    pub(crate) release: &'a str,
+    pub(crate) namespace: &'a str,
+    pub(crate) profile: Option<&'a str>,
+    pub(crate) chart_version: Option<&'a str>,
+    /// Values files as passed to helm, with the path they were read from
+    pub(crate) values: &'a [(&'a Path, &'a Path)],
+}
+
+impl<'a> Audited<'a> {
+    pub(crate) fn new(action: ReleaseAction, release: &'a str, namespace: &'a str) -> Self {
+        Self {
+            action,
+            release,
+            namespace,
+            profile: None,
+            chart_version: None,
+            values: &[],
+        }
+    }
+}
+
+/// Filters of the audit log. Unset filters match everything
+#[derive(Debug, Default)]
+pub struct AuditQuery {
+    pub release: Option<String>,
+    pub namespace: Option<String>,
+    pub since: Option<Timestamp>,
+    pub until: Option<Timestamp>,
+}
+
+impl AuditQuery {
+    fn matches(&self, record: &AuditRecord) -> bool {
+        self.release.as_ref().is_none_or(|r| *r == record.release)
+            && self
+                .namespace
+                .as_ref()
+                .is_none_or(|n| *n == record.namespace)
+            && self.since.is_none_or(|since| record.timestamp >= since)
+            && self.until.is_none_or(|until| record.timestamp <= until)
+    }
+}
+
+/// Appends a record of the executed command to the audit log.
+/// A log that cannot be written is reported, but does not fail the command
+pub(crate) fn record(audited: &Audited, execution: &Execution) {
+    let Some(log) = log_file() else {
+        warn!("No location for the audit log found. Set audit_log in the settings");
+        return;
+    };
+
+    let record = create_record(audited, execution, origin());
+    if let Err(err) = append(&log, &record) {
+        message!("Failed to write the audit log {}: {err}", log.display());
+    }
+}
+
+fn create_record(audited: &Audited, execution: &Execution, origin: &Origin) -> AuditRecord {
+    AuditRecord {
+        timestamp: Timestamp::now(),
+        user: origin.user.clone(),
+        host: origin.host.clone(),
+        context: origin.context.clone(),
+        command: origin.command.clone(),
+        action: audited.action,
+        release: audited.release.to_string(),
+        namespace: audited.namespace.to_string(),
+        profile: audited.profile.map(str::to_string),
+        chart_version: audited.chart_version.map(str::to_string),
+        values: audited
+            .values
+            .iter()
+            .filter_map(|(passed, source)| {
+                let hash = hash_file(passed)
+                    .inspect_err(|err| warn!("Failed to hash {passed:?}: {err}"))
+                    .ok()?;
+                Some((source.display().to_string(), hash))
+            })
+            .collect(),
+        success: execution.exit_code == Some(0),
+        exit_code: execution.exit_code,
+        duration_ms: execution.duration.as_millis(),
+    }
+}
+
+fn append(log: &Path, record: &AuditRecord) -> Result<()> {
+    if let Some(dir) = log.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+    // A single write keeps lines of concurrent processes apart
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log)?
+        .write_all(line.as_bytes())?;
+    info!("Appended to the audit log {log:?}");
+
+    Ok(())
+}
+
+/// Records of the audit log matching the query, oldest first
+pub(crate) fn read(query: &AuditQuery) -> Result<Vec<AuditRecord>> {
+    let Some(log) = log_file().filter(|log| log.exists()) else {
+        return Ok(Vec::new());
+    };
+
+    Ok(parse(&fs::read_to_string(&log)?)
+        .into_iter()
+        .filter(|record| query.matches(record))
+        .collect())
+}
+
+/// Lines that are not records, e.g. of a crashed write, are skipped
+fn parse(log: &str) -> Vec<AuditRecord> {
+    log.lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            serde_json::from_str(line)
+                .inspect_err(|err| warn!("Skipping invalid line of the audit log: {err}"))
+                .ok()
+        })
+        .collect()
+}
+
+/// Parses a point in time: a timestamp (2026-10-01T12:00:00Z), a date in UTC (2026-10-01)
+/// or a duration before now (7d, 12h)
+pub fn parse_time(time: &str) -> Result<Timestamp> {
+    if let Ok(timestamp) = time.parse::<Timestamp>() {
+        return Ok(timestamp);
+    }
+    if let Ok(date) = time.parse::<Date>() {
+        return date
+            .to_zoned(TimeZone::UTC)
+            .map(|zoned| zoned.timestamp())
+            .map_err(|_| Error::InvalidTime(time.to_string()));
+    }
+
+    time.parse::<jiff::Span>()
+        .ok()
+        .and_then(|ago| {
+            Zoned::now()
+                .with_time_zone(TimeZone::UTC)
+                .checked_sub(ago)
+                .ok()
+        })
+        .map(|zoned| zoned.timestamp())
+        .ok_or_else(|| Error::InvalidTime(time.to_string()))
+}
+
+/// Configured audit log, or the default in the XDG state directory
+fn log_file() -> Option<PathBuf> {
+    if let Some(log) = &settings::get().audit_log {
+        return Some(log.clone());
+    }
+
+    Some(settings::state_dir()?.join("audit.jsonl"))
+}
+
+fn hash_file(path: &Path) -> Result<String> {
+    Ok(format!("{:x}", Sha256::digest(fs::read(path)?)))
+}
+
+/// Who is running shippr. Also used to name the holder of locks
+pub(crate) fn origin() -> &'static Origin {
+    ORIGIN.get_or_init(|| Origin {
+        user: env::var("USER")
+            .or_else(|_| env::var("USERNAME"))
+            .unwrap_or_else(|_| String::from("unknown")),
+        host: host_name(),
+        context: current_context(),
+        command: env::args().collect::<Vec<_>>().join(" "),
+    })
+}
+
+fn host_name() -> String {
+    fs::read_to_string("/etc/hostname")
+        .ok()
+        .or_else(|| env::var("HOSTNAME").ok())
+        .or_else(|| env::var("COMPUTERNAME").ok())
+        .map(|host| host.trim().to_string())
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| String::from("unknown"))
+}
+
+/// None if kubectl is missing or has no current context. Its errors are not printed,
+/// as commands that only run helm are audited too
+fn current_context() -> Option<String> {
+    let execution = create_current_context().execute_captured().ok()?;
+    if execution.exit_code != Some(0) {
+        debug!("Found no kubectl context: {}", execution.stderr.trim());
+        return None;
+    }
+
+    Some(execution.stdout.trim().to_string()).filter(|context| !context.is_empty())
+}
+
+fn create_current_context() -> Command {
+    let mut command = Command::kubectl();
+    command.args(["config", "current-context"]);
+
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path, time::Duration};
+
+    use super::{AuditQuery, Audited, Origin, append, create_record, parse, parse_time};
+    use crate::{command::Execution, output::ReleaseAction};
+
+    type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+    fn origin() -> Origin {
+        Origin {
+            user: String::from("alice"),
+            host: String::from("laptop"),
+            context: Some(String::from("kind-dev")),
+            command: String::from("shippr deploy nginx -p prod"),
+        }
+    }
+
+    fn execution(exit_code: i32) -> Execution {
+        Execution {
+            command: String::from("helm upgrade --install"),
+            exit_code: Some(exit_code),
+            duration: Duration::from_millis(1500),
+            stdout: String::new(),
+            stderr: String::new(),
+        }
+    }
+
+    #[test]
+    fn record_appended_with_values_hashes() -> TestResult {
+        // given
+        let dir = tempfile::tempdir()?;
+        let values = dir.path().join("values-default.yaml");
+        fs::write(&values, "replicas: 1\n")?;
+        let values = [(values.as_path(), Path::new("nginx/values-default.yaml"))];
+        let audited = Audited {
+            profile: Some("prod"),
+            chart_version: Some("1.2.0"),
+            values: &values,
+            ..Audited::new(ReleaseAction::Deploy, "nginx", "dev")
+        };
+        let log = dir.path().join("state/audit.jsonl");
+
+        // when
+        append(&log, &create_record(&audited, &execution(0), &origin()))?;
+        append(&log, &create_record(&audited, &execution(1), &origin()))?;
+
+        // then
+        let result = parse(&fs::read_to_string(&log)?);
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].user, "alice");
+        assert_eq!(result[0].context.as_deref(), Some("kind-dev"));
+        assert_eq!(result[0].profile.as_deref(), Some("prod"));
+        assert_eq!(
+            result[0].values["nginx/values-default.yaml"],
+            "64c510504df97ba5ad76d9dab24c7550b4b53a6d31adc47a75e6efbe8851ae8c"
+        );
+        assert!(result[0].success);
+        assert_eq!(result[1].exit_code, Some(1));
+        assert!(!result[1].success);
+
+        Ok(())
+    }
+
+    #[test]
+    fn query_by_release_namespace_and_time() -> TestResult {
+        // given
+        let nginx = create_record(
+            &Audited::new(ReleaseAction::Deploy, "nginx", "dev"),
+            &execution(0),
+            &origin(),
+        );
+        let redis = create_record(
+            &Audited::new(ReleaseAction::Undeploy, "redis", "prod"),
+            &execution(0),
+            &origin(),
+        );
+
+        // when
+        let by_release = AuditQuery {
+            release: Some(String::from("nginx")),
+            ..Default::default()
+        };
+        let by_namespace = AuditQuery {
+            namespace: Some(String::from("prod")),
+            ..Default::default()
+        };
+        let future = AuditQuery {
+            since: Some(parse_time("2999-01-01")?),
+            ..Default::default()
+        };
+        let past = AuditQuery {
+            until: Some(parse_time("1d")?),
+            ..Default::default()
+        };
+
+        // then
+        assert!(by_release.matches(&nginx) && !by_release.matches(&redis));
+        assert!(by_namespace.matches(&redis) && !by_namespace.matches(&nginx));
+        assert!(!future.matches(&nginx));
+        assert!(!past.matches(&nginx));
+
+        Ok(())
+    }
+
+    #[test]
+    fn time_formats() -> TestResult {
+        assert_eq!(
+            parse_time("2026-10-01T12:00:00Z")?.to_string(),
+            "2026-10-01T12:00:00Z"
+        );
+        assert_eq!(
+            parse_time("2026-10-01")?.to_string(),
+            "2026-10-01T00:00:00Z"
+        );
+        assert!(parse_time("7d").is_ok());
+        assert!(parse_time("last week").is_err());
+
+        Ok(())
+    }
+}
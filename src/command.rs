@@ -1,24 +1,55 @@
 use std::{
     ffi::OsStr,
-    io::{Write, stderr, stdout},
-    process,
+    fmt::{self, Display, Formatter},
+    io::{Write, stderr},
+    path::Path,
+    process::{self, Stdio},
+    time::{Duration, Instant},
 };
 
 use tracing::info;
 
-use crate::Result;
+use crate::{Error, Result, settings, tools::Tool};
+
+mod live;
 
 pub struct Command {
     command: process::Command,
+    /// Release the command belongs to. Its output lines are prefixed with it if enabled
+    release: Option<String>,
+}
+
+/// Information about an executed command
+#[derive(Debug)]
+pub struct Execution {
+    pub(crate) command: String,
+    /// None if the process was terminated by a signal
+    pub(crate) exit_code: Option<i32>,
+    pub(crate) duration: Duration,
+    /// Everything the command wrote to stdout
+    pub(crate) stdout: String,
+    /// Everything the command wrote to stderr
+    pub(crate) stderr: String,
 }
 
 impl Command {
     pub(crate) fn new(command: &str) -> Self {
         Self {
             command: process::Command::new(command),
+            release: None,
         }
     }
 
+    /// helm, as configured in the settings
+    pub(crate) fn helm() -> Self {
+        Self::new(Tool::Helm.program())
+    }
+
+    /// kubectl, as configured in the settings
+    pub(crate) fn kubectl() -> Self {
+        Self::new(Tool::Kubectl.program())
+    }
+
     /// The advantage of AsRef vs &str
     /// AsRef: The callee can use &str or String and the method takes the ref when needed
     /// &str: The callee must borrow his String if he has one
@@ -40,19 +71,84 @@ impl Command {
         self
     }
 
-    /// No-value return
-    pub fn execute(mut self) -> Result<()> {
+    pub(crate) fn env<K: AsRef<OsStr>, V: AsRef<OsStr>>(&mut self, key: K, value: V) -> &mut Self {
+        self.command.env(key, value);
+
+        self
+    }
+
+    pub(crate) fn current_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self {
+        self.command.current_dir(dir);
+
+        self
+    }
+
+    pub(crate) fn release(&mut self, release: &str) -> &mut Self {
+        self.release = Some(release.to_string());
+
+        self
+    }
+
+    /// No-value return. The output is printed while the command runs
+    pub fn execute(mut self) -> Result<Execution> {
         info!("Running command {:?}", self.command.get_program());
         info!("Command args: {:?}", self.command.get_args());
 
+        let started = Instant::now();
+        let mut child = self
+            .command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+            unreachable!("stdout and stderr are piped");
+        };
+
+        let label = self.label();
+        let prefix = self
+            .release
+            .as_deref()
+            .filter(|_| settings::get().prefix_output);
+        let (stdout, stderr) = live::LiveOutput::new(&label, prefix).run(stdout, stderr);
+        let status = child.wait()?;
+
+        Ok(Execution {
+            command: self.to_string(),
+            exit_code: status.code(),
+            duration: started.elapsed(),
+            stdout,
+            stderr,
+        })
+    }
+
+    /// Like [Command::execute], but nothing is printed.
+    /// For commands running at the same time, whose output would be interleaved
+    pub(crate) fn execute_captured(mut self) -> Result<Execution> {
+        info!("Running command {:?}", self.command.get_program());
+        info!("Command args: {:?}", self.command.get_args());
+
+        let started = Instant::now();
         let output = self.command.output()?;
-        stdout().write_all(&output.stdout)?;
-        stderr().write_all(&output.stderr)?;
 
-        Ok(())
+        Ok(Execution {
+            command: self.to_string(),
+            exit_code: output.status.code(),
+            duration: started.elapsed(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
     }
 
-    /// stdout return
+    /// Program and subcommand, e.g. helm upgrade
+    fn label(&self) -> String {
+        let program = self.command.get_program().to_string_lossy();
+        match self.command.get_args().next() {
+            Some(subcommand) => format!("{program} {}", subcommand.to_string_lossy()),
+            None => program.to_string(),
+        }
+    }
+
+    /// stdout return. Fails if the command was not successful
     pub fn output(mut self) -> Result<String> {
         info!("Running command {:?}", self.command.get_program());
         info!("Command args: {:?}", self.command.get_args());
@@ -60,10 +156,26 @@ impl Command {
         let output = self.command.output()?;
         stderr().write_all(&output.stderr)?;
 
+        if !output.status.success() {
+            return Err(Error::CommandFailed(self.to_string(), output.status.code()));
+        }
+
         Ok(String::from_utf8(output.stdout)?)
     }
 }
 
+impl Display for Command {
+    /// Renders the command the way it would be typed into a shell
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.command.get_program().to_string_lossy())?;
+        for arg in self.command.get_args() {
+            write!(f, " {}", arg.to_string_lossy())?;
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 impl Command {
     pub(crate) fn get_program(&self) -> &OsStr {
@@ -73,4 +185,53 @@ impl Command {
     pub(crate) fn get_args(&self) -> Vec<&OsStr> {
         self.command.get_args().collect()
     }
+
+    pub(crate) fn get_envs(&self) -> Vec<(&OsStr, Option<&OsStr>)> {
+        self.command.get_envs().collect()
+    }
+
+    pub(crate) fn get_current_dir(&self) -> Option<&Path> {
+        self.command.get_current_dir()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Command;
+
+    type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+    #[test]
+    fn execute_captures_stderr_and_exit_code() -> TestResult {
+        // given
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo progress; echo failed >&2; exit 3"]);
+
+        // when
+        let result = command.execute()?;
+
+        // then
+        assert_eq!(result.exit_code, Some(3));
+        assert_eq!(result.stderr, "failed\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn display_program_and_args() {
+        // given
+        let mut command = Command::new("helm");
+        command
+            .args(["uninstall", "TestRelease"])
+            .args(["--namespace", "TestNamespace"]);
+
+        // when
+        let result = command.to_string();
+
+        // then
+        assert_eq!(
+            result,
+            "helm uninstall TestRelease --namespace TestNamespace"
+        );
+    }
 }
@@ -0,0 +1,159 @@
+use std::{
+    io::{BufRead, BufReader, IsTerminal, Read, Write, stderr},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::output;
+
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+const TICK: Duration = Duration::from_millis(100);
+/// Clears the current line of the terminal
+const CLEAR_LINE: &str = "\r\x1b[2K";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Prints the output of a running command line by line.
+/// On terminals the elapsed time is shown below the output until the command finishes
+pub(super) struct LiveOutput<'a> {
+    label: &'a str,
+    prefix: Option<&'a str>,
+    started: Instant,
+    indicator: bool,
+    finished: AtomicBool,
+    /// Lines and the indicator must not be written at the same time
+    terminal: Mutex<()>,
+}
+
+impl<'a> LiveOutput<'a> {
+    pub(super) fn new(label: &'a str, prefix: Option<&'a str>) -> Self {
+        Self {
+            label,
+            prefix,
+            started: Instant::now(),
+            indicator: stderr().is_terminal(),
+            finished: AtomicBool::new(false),
+            terminal: Mutex::new(()),
+        }
+    }
+
+    /// Streams both outputs until they are closed and returns what was captured of them
+    pub(super) fn run<O: Read + Send, E: Read + Send>(
+        &self,
+        stdout: O,
+        stderr: E,
+    ) -> (String, String) {
+        thread::scope(|scope| {
+            if self.indicator {
+                scope.spawn(|| self.tick());
+            }
+            let stdout = scope.spawn(|| self.stream(stdout, Stream::Stdout));
+            let stderr = scope.spawn(|| self.stream(stderr, Stream::Stderr));
+
+            let captured = (
+                stdout.join().unwrap_or_default(),
+                stderr.join().unwrap_or_default(),
+            );
+            self.finished.store(true, Ordering::Relaxed);
+
+            captured
+        })
+    }
+
+    fn stream<R: Read>(&self, source: R, stream: Stream) -> String {
+        let mut reader = BufReader::new(source);
+        let mut captured = String::new();
+        let mut buffer = Vec::new();
+
+        while reader.read_until(b'\n', &mut buffer).unwrap_or(0) > 0 {
+            let line = String::from_utf8_lossy(&buffer);
+            self.write_line(stream, &prefixed(self.prefix, &line));
+            captured.push_str(&line);
+            buffer.clear();
+        }
+
+        captured
+    }
+
+    fn write_line(&self, stream: Stream, line: &str) {
+        let _terminal = self.terminal.lock();
+        if self.indicator {
+            eprint!("{CLEAR_LINE}");
+        }
+
+        let _ = match stream {
+            Stream::Stdout => output::passthrough(line.as_bytes()),
+            Stream::Stderr => stderr().write_all(line.as_bytes()),
+        };
+        if self.indicator {
+            self.draw_indicator();
+        }
+    }
+
+    fn tick(&self) {
+        while !self.finished.load(Ordering::Relaxed) {
+            if let Ok(_terminal) = self.terminal.lock() {
+                self.draw_indicator();
+            }
+            thread::sleep(TICK);
+        }
+
+        let _terminal = self.terminal.lock();
+        eprint!("{CLEAR_LINE}");
+    }
+
+    fn draw_indicator(&self) {
+        let elapsed = self.started.elapsed();
+        let frame = SPINNER[(elapsed.as_millis() / TICK.as_millis()) as usize % SPINNER.len()];
+        eprint!(
+            "{CLEAR_LINE}{frame} {} ({}s)",
+            self.label,
+            elapsed.as_secs()
+        );
+        let _ = stderr().flush();
+    }
+}
+
+/// Every line is terminated, also the last one of a command
+fn prefixed(prefix: Option<&str>, line: &str) -> String {
+    let line = line.trim_end_matches(['\r', '\n']);
+    match prefix {
+        Some(prefix) => format!("[{prefix}] {line}\n"),
+        None => format!("{line}\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LiveOutput, prefixed};
+
+    #[test]
+    fn lines_prefixed_with_release() {
+        assert_eq!(
+            prefixed(Some("nginx"), "Release \"nginx\" has been upgraded\n"),
+            "[nginx] Release \"nginx\" has been upgraded\n"
+        );
+        assert_eq!(prefixed(None, "no newline"), "no newline\n");
+    }
+
+    #[test]
+    fn both_outputs_captured() {
+        // given
+        let live = LiveOutput::new("helm", None);
+
+        // when
+        let (stdout, stderr) = live.run(&b"first\nsecond\n"[..], &b"warning"[..]);
+
+        // then
+        assert_eq!(stdout, "first\nsecond\n");
+        assert_eq!(stderr, "warning");
+    }
+}
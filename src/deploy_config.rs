@@ -1,21 +1,69 @@
 use config::File;
-use serde::Deserialize;
-use std::{fs, path::Path};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 use tracing::{debug, warn};
 
-use crate::{Error, Result, command::Command};
+use crate::{Error, Result, command::Command, lint::Lint, policy::Policy, settings};
+
+mod validation;
+
+/// Location of the JSON Schema of the deployment file, as published in the repository
+pub(crate) const SCHEMA_URL: &str =
+    "https://raw.githubusercontent.com/NiclasGH/shippr/main/schema/deployment.schema.json";
+
+/// Written by import if the repository of a chart is unknown. Reported by the validation
+pub(crate) const LOCATION_PLACEHOLDER: &str = "REPLACE-ME: URL of the chart repository";
+
+/// Formats the deployment file can be written in
+const EXTENSIONS: [&str; 7] = ["yaml", "yml", "toml", "json", "json5", "ini", "ron"];
+
+/// A deployment file that could not be read, with the reason
+pub(crate) type InvalidFile = (PathBuf, Error);
 
 #[derive(Debug)]
 pub struct Deployment {
     release: Release,
+    /// Directory of the deployment file, containing the values files
+    dir: PathBuf,
+    /// Set for deployment files named like `deployment.<variant>.yaml`
+    variant: Option<String>,
     pub chart: DeployChart,
 }
-#[derive(Debug, Deserialize)]
+/// Deployment file of shippr. The name of the release is the name of the directory
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[schemars(rename = "Deployment")]
 pub struct DeployChart {
+    /// Name of the Chart. NOT the name of the release
     name: String,
+    /// Version of the Chart. Either a semantic version or a version range
+    #[schemars(extend("type" = ["string", "number", "null"]))]
+    #[serde(skip_serializing_if = "Option::is_none")]
     version: Option<String>,
+    /// Namespace in which the Chart is being released
+    #[schemars(regex(pattern = r"^[a-z0-9]([-a-z0-9]*[a-z0-9])?$"), length(max = 63))]
     pub namespace: String,
     location: Location,
+    /// Runs the tests of the chart after every deploy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    run_tests: Option<bool>,
+    /// Restores the last successful revision if a deploy fails
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auto_rollback: Option<bool>,
+    /// Commands run before and after every deploy and undeploy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hooks: Option<Hooks>,
+    /// Policies that are not checked for this release by check --policies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suppress_policies: Option<Vec<Policy>>,
+    /// Lints of the values files that are not reported for this release by lint-values and check
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suppress_lints: Option<Vec<Lint>>,
 }
 #[derive(Debug)]
 struct Release {
@@ -30,61 +78,360 @@ impl From<&str> for Release {
     }
 }
 
-#[derive(Debug, Deserialize)]
+/// Location where to find the Chart. Exactly one must be set
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[schemars(extend("minProperties" = 1, "maxProperties" = 1))]
 struct Location {
+    /// URL of the chart repository, or an alias of a repository configured in shippr.toml
+    #[serde(skip_serializing_if = "Option::is_none")]
     repo: Option<String>,
+    /// OCI registry containing the chart, e.g. oci://registry.example.com/charts.
+    /// The name of the chart is appended to it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    oci: Option<String>,
+    /// Path of the chart directory. Relative paths are resolved from the execution directory
+    #[serde(skip_serializing_if = "Option::is_none")]
     local: Option<String>,
 }
 
+/// Shell commands run in the directory of the release around deploys and undeploys
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct Hooks {
+    /// Run before the deploy. A failing command aborts the deploy
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pre_deploy: Vec<String>,
+    /// Run after a successful deploy
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    post_deploy: Vec<String>,
+    /// Run before the undeploy. A failing command aborts the undeploy
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pre_undeploy: Vec<String>,
+    /// Run after a successful undeploy
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    post_undeploy: Vec<String>,
+}
+
+/// When hooks are run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HookStage {
+    PreDeploy,
+    PostDeploy,
+    PreUndeploy,
+    PostUndeploy,
+}
+
+impl fmt::Display for HookStage {
+    /// Named like the key in the deployment file
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            HookStage::PreDeploy => "preDeploy",
+            HookStage::PostDeploy => "postDeploy",
+            HookStage::PreUndeploy => "preUndeploy",
+            HookStage::PostUndeploy => "postUndeploy",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// One of the locations a chart can be found at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChartLocation {
+    Repo(String),
+    Oci(String),
+    Local(String),
+}
+
+impl FromStr for ChartLocation {
+    type Err = Error;
+
+    /// Repositories are detected by their http(s) URL and registries by oci://.
+    /// Anything else is a local path
+    fn from_str(s: &str) -> Result<Self> {
+        let location = s.trim();
+        if location.is_empty() {
+            return Err(Error::MissingChartLocation);
+        }
+
+        if location.starts_with("http://") || location.starts_with("https://") {
+            Ok(ChartLocation::Repo(location.to_string()))
+        } else if location.starts_with("oci://") {
+            Ok(ChartLocation::Oci(location.to_string()))
+        } else {
+            Ok(ChartLocation::Local(location.to_string()))
+        }
+    }
+}
+
+impl From<ChartLocation> for Location {
+    fn from(value: ChartLocation) -> Self {
+        match value {
+            ChartLocation::Repo(repo) => Location {
+                repo: Some(repo),
+                ..Default::default()
+            },
+            ChartLocation::Oci(oci) => Location {
+                oci: Some(oci),
+                ..Default::default()
+            },
+            ChartLocation::Local(local) => Location {
+                local: Some(local),
+                ..Default::default()
+            },
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct DeploymentFileName(String);
 
+impl From<String> for DeploymentFileName {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
 impl Default for DeploymentFileName {
     fn default() -> Self {
         Self(String::from("deployment"))
     }
 }
 
+impl DeploymentFileName {
+    pub(crate) fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Checks if the directory contains any deployment file in any of the supported formats
+    pub(crate) fn exists_in(&self, dir: &Path) -> bool {
+        !self.find_all_in(dir).is_empty()
+    }
+
+    /// Finds the deployment files in any of the supported formats, together with their variant.
+    /// If a deployment file exists in multiple formats, the first one in alphabetical order is used.
+    /// A variant is only a release if it has its own values-default file, so copies like
+    /// `deployment.old.yaml` or `deployment.schema.json` are ignored
+    fn find_all_in(&self, dir: &Path) -> Vec<(PathBuf, Option<String>)> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut files: Vec<_> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect();
+        files.sort();
+
+        let mut found: Vec<(PathBuf, Option<String>)> = Vec::new();
+        for path in &files {
+            let Some(variant) = self.variant_of(path) else {
+                continue;
+            };
+            if let Some(variant) = variant
+                .as_deref()
+                .filter(|variant| !has_values_default(&files, variant))
+            {
+                warn!("Ignoring {path:?} as there is no values-default.{variant} file for it");
+                continue;
+            }
+            if found.iter().any(|(_, v)| *v == variant) {
+                warn!("Ignoring {path:?} as the deployment file exists in another format");
+                continue;
+            }
+            found.push((path.clone(), variant));
+        }
+
+        found
+    }
+
+    /// Returns None if the file is no deployment file.
+    /// Files named `<name>.<variant>.<extension>` define a further release of the directory
+    fn variant_of(&self, path: &Path) -> Option<Option<String>> {
+        let file_name = path.file_name()?.to_str()?;
+        let (stem, extension) = file_name.rsplit_once('.')?;
+        if !EXTENSIONS.contains(&extension) {
+            return None;
+        }
+        if stem == self.0 {
+            return Some(None);
+        }
+
+        stem.strip_prefix(self.0.as_str())?
+            .strip_prefix('.')
+            .filter(|variant| !variant.is_empty())
+            .map(|variant| Some(variant.to_string()))
+    }
+}
+
+/// Checks if any of the files is the values-default file of the variant, in any format
+fn has_values_default(files: &[PathBuf], variant: &str) -> bool {
+    let values_default = format!("values-default.{variant}");
+    files.iter().any(|file| {
+        file.file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.rsplit_once('.'))
+            .is_some_and(|(stem, _)| stem == values_default)
+    })
+}
+
+/// Defines how deployments are searched for below a directory
+#[derive(Debug)]
+pub struct Discovery {
+    /// How many directory levels are searched. 1 only searches the direct children
+    pub depth: usize,
+    /// Aborts if any deployment file is invalid instead of skipping it
+    pub strict: bool,
+}
+
+/// JSON Schema of the deployment file, usable by editors and YAML language servers
+pub fn schema() -> serde_json::Value {
+    schemars::schema_for!(DeployChart).to_value()
+}
+
 impl Deployment {
-    pub fn new(base_path: &Path, file_name: Option<DeploymentFileName>) -> Result<Self> {
+    /// Reads every deployment file of the directory. Each of them is a separate release
+    pub fn all_in(base_path: &Path, file_name: &DeploymentFileName) -> Result<Vec<Self>> {
         if !base_path.exists() && !base_path.is_dir() {
             return Err(Error::InvalidDirectory);
         }
         let absolute_path = fs::canonicalize(base_path)?;
-
         let directory_name = Self::dir_name(&absolute_path)?;
-        let absolute_path_string = base_path
-            .as_os_str()
-            .to_str()
-            .ok_or(Error::InvalidDirectory)?;
 
-        let file_name = file_name
-            .unwrap_or_else(|| {
-                let default = DeploymentFileName::default();
-                debug!("File name not provided. Taking default: {default:?}");
-                default
-            })
-            .0;
+        let files = file_name.find_all_in(base_path);
+        if files.is_empty() {
+            return Err(Error::DeploymentFileMissing(base_path.join(&file_name.0)));
+        }
+        debug!("Found the following deployment files: {files:?}");
 
-        let config_path = format!("{absolute_path_string}/{file_name}");
+        files
+            .into_iter()
+            .map(|(path, variant)| Self::from_file(&path, directory_name, variant))
+            .collect()
+    }
 
-        let chart: DeployChart = config::Config::builder()
-            .add_source(File::with_name(&config_path))
-            .build()?
-            .try_deserialize()?;
+    fn from_file(
+        config_path: &Path,
+        directory_name: &str,
+        variant: Option<String>,
+    ) -> Result<Self> {
+        let release_name = match &variant {
+            Some(variant) => format!("{directory_name}-{variant}"),
+            None => directory_name.to_string(),
+        };
+
+        let mut builder = config::Config::builder().add_source(File::from(config_path));
+        let repository_alias = builder
+            .build_cloned()?
+            .get_string("location.repo")
+            .ok()
+            .and_then(|repo| settings::get().repositories.get(&repo));
+        if let Some(url) = repository_alias {
+            debug!("Resolved the repository alias of {config_path:?} to {url}");
+            builder = builder.set_override("location.repo", url.as_str())?;
+        }
+        let config = builder.build()?;
 
-        if chart.has_duplicate_location() {
-            warn!(
-                "The charts have a duplicate location. Please ensure your deployment file only has 1 location"
-            );
-            return Err(Error::DuplicateLocation);
+        let problems = validation::validate(
+            config_path,
+            &config.clone().try_deserialize()?,
+            &release_name,
+        );
+        if !problems.is_empty() {
+            warn!("The deployment file {config_path:?} is invalid");
+            return Err(Error::InvalidDeployment(problems));
         }
 
+        let chart: DeployChart = config.try_deserialize()?;
+
         Ok(Deployment {
-            release: Release::from(directory_name),
+            release: Release::from(release_name.as_str()),
+            dir: config_path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            variant,
             chart,
         })
     }
+
+    /// Finds all deployments below the given directory.
+    /// A directory containing a deployment file is a release and is not searched any further.
+    pub fn discover(
+        dir: &Path,
+        file_name: &DeploymentFileName,
+        discovery: &Discovery,
+    ) -> Result<Vec<Self>> {
+        let (deployments, invalid) = Self::discover_all(dir, file_name, discovery.depth)?;
+
+        let errors: Vec<String> = invalid
+            .into_iter()
+            .map(|(file, err)| format!("{}: {err}", file.display()))
+            .collect();
+        if !errors.is_empty() {
+            if discovery.strict {
+                return Err(Error::InvalidDeploymentFiles(errors));
+            }
+            for err in errors {
+                warn!("Skipping invalid deployment file: {err}");
+            }
+        }
+
+        Ok(deployments)
+    }
+
+    /// Finds all deployments below the given directory, like [Deployment::discover].
+    /// Invalid deployment files are returned with their error instead
+    pub(crate) fn discover_all(
+        dir: &Path,
+        file_name: &DeploymentFileName,
+        depth: usize,
+    ) -> Result<(Vec<Self>, Vec<InvalidFile>)> {
+        let mut deployments = Vec::new();
+        let mut errors = Vec::new();
+        Self::discover_in(dir, file_name, depth, &mut deployments, &mut errors)?;
+
+        Ok((deployments, errors))
+    }
+
+    fn discover_in(
+        dir: &Path,
+        file_name: &DeploymentFileName,
+        depth: usize,
+        deployments: &mut Vec<Self>,
+        errors: &mut Vec<InvalidFile>,
+    ) -> Result<()> {
+        if depth == 0 {
+            return Ok(());
+        }
+
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() || entry.file_name().to_string_lossy().starts_with(".")
+            {
+                continue;
+            }
+
+            let path = entry.path();
+            let files = file_name.find_all_in(&path);
+            if files.is_empty() {
+                debug!("No deployment file found in {path:?}. Searching its subdirectories");
+                Self::discover_in(&path, file_name, depth - 1, deployments, errors)?;
+                continue;
+            }
+
+            let directory_name = entry.file_name().to_string_lossy().to_string();
+            for (file, variant) in files {
+                match Deployment::from_file(&file, &directory_name, variant) {
+                    Ok(deployment) => deployments.push(deployment),
+                    Err(err) => errors.push((file, err)),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn dir_name(path: &Path) -> Result<&str> {
         let name = path
             .file_name()
@@ -94,6 +441,20 @@ impl Deployment {
         Ok(name)
     }
 
+    pub fn release_name(&self) -> &str {
+        &self.release.name
+    }
+
+    pub(crate) fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Variant of the deployment file. Its values files are named the same way,
+    /// e.g. `values-default.<variant>.yaml`
+    pub(crate) fn variant(&self) -> Option<&str> {
+        self.variant.as_deref()
+    }
+
     pub fn append_deployment_information(&self, command: &mut Command) {
         self.chart.append_chart_information(command);
         self.release.append_release_information(command);
@@ -113,8 +474,84 @@ impl Release {
 }
 
 impl DeployChart {
-    fn has_duplicate_location(&self) -> bool {
-        self.location.has_duplicate_location()
+    pub(crate) fn new(
+        name: String,
+        version: Option<String>,
+        namespace: String,
+        location: ChartLocation,
+    ) -> Self {
+        Self {
+            name,
+            version,
+            namespace,
+            location: location.into(),
+            run_tests: None,
+            auto_rollback: None,
+            hooks: None,
+            suppress_policies: None,
+            suppress_lints: None,
+        }
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Path of the chart, if it is local
+    pub(crate) fn local_path(&self) -> Option<&str> {
+        self.location.local.as_deref()
+    }
+
+    pub(crate) fn run_tests(&self) -> bool {
+        self.run_tests.unwrap_or(false)
+    }
+
+    pub(crate) fn auto_rollback(&self) -> bool {
+        self.auto_rollback.unwrap_or(false)
+    }
+
+    pub(crate) fn suppressed_policies(&self) -> &[Policy] {
+        self.suppress_policies.as_deref().unwrap_or_default()
+    }
+
+    pub(crate) fn suppressed_lints(&self) -> &[Lint] {
+        self.suppress_lints.as_deref().unwrap_or_default()
+    }
+
+    /// Commands of the hooks of the stage, in the order they are run
+    pub(crate) fn hooks(&self, stage: HookStage) -> &[String] {
+        let Some(hooks) = &self.hooks else {
+            return &[];
+        };
+
+        match stage {
+            HookStage::PreDeploy => &hooks.pre_deploy,
+            HookStage::PostDeploy => &hooks.post_deploy,
+            HookStage::PreUndeploy => &hooks.pre_undeploy,
+            HookStage::PostUndeploy => &hooks.post_undeploy,
+        }
+    }
+
+    /// Validates the chart as if it was read from the file of the release
+    pub(crate) fn validate(&self, file: &Path, release_name: &str) -> Result<()> {
+        let problems = validation::validate(file, &serde_json::to_value(self)?, release_name);
+        if !problems.is_empty() {
+            return Err(Error::InvalidDeployment(problems));
+        }
+
+        Ok(())
+    }
+
+    /// Appends everything needed to find the chart, without anything release specific
+    pub(crate) fn append_chart_source(&self, command: &mut Command) {
+        self.append_chart_location(command);
+        if let Some(version) = &self.version {
+            command.args(["--version", version]);
+        }
     }
 
     fn append_chart_information(&self, command: &mut Command) {
@@ -135,59 +572,53 @@ impl DeployChart {
             command.arg(v);
         } else if let Some(v) = self.location.repo.clone() {
             command.arg(&self.name).args(["--repo", &v]);
+        } else if let Some(v) = &self.location.oci {
+            command.arg(format!("{}/{}", v.trim_end_matches('/'), self.name));
         }
     }
 }
 
-impl Location {
-    fn has_duplicate_location(&self) -> bool {
-        self.local.is_some() == self.repo.is_some()
-    }
-}
-
 #[cfg(test)]
 mod tests {
-    use std::io::Write;
-    use std::matches;
-    use tempfile::Builder;
-
-    use super::{Deployment, DeploymentFileName};
+    use super::{ChartLocation, DeployChart, Deployment, DeploymentFileName, Discovery};
+    use crate::command::Command;
     type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
 
+    const VALID_DEPLOYMENT: &str = r#"
+        name: TestName
+        namespace: test-namespace
+        location:
+            repo: https://test.repo
+        "#;
+
     #[test]
     fn file_deserialized_correctly() -> TestResult {
         // given
-        let mut deployment_file = Builder::new()
-            .prefix("deployment")
-            .suffix(".yaml")
-            .tempfile()?;
+        let root = tempfile::tempdir()?;
+        let dir = root.path().join("test-release");
+        std::fs::create_dir(&dir)?;
         let file_content = r#"
         name: TestName
-        version: TestVersion
-        namespace: TestNamespace
+        version: 1.2.3
+        namespace: test-namespace
         location:
-            repo: TestRepo
+            repo: https://test.repo
         "#;
-        writeln!(&mut deployment_file, "{file_content}")?;
-        let binding = deployment_file.into_temp_path();
-        let file_name = binding.file_name().unwrap().to_str().unwrap();
+        std::fs::write(dir.join("deployment.yaml"), file_content)?;
 
         // when
-        let result = Deployment::new(
-            &std::env::temp_dir(),
-            Some(DeploymentFileName(file_name.to_string())),
-        )?;
+        let result = Deployment::all_in(&dir, &DeploymentFileName::default())?.remove(0);
 
         // then
         assert_eq!(result.chart.name, "TestName");
-        assert_eq!(result.chart.version, Some(String::from("TestVersion")));
-        assert_eq!(result.chart.namespace, String::from("TestNamespace"));
-        assert_eq!(result.chart.location.repo, Some(String::from("TestRepo")));
+        assert_eq!(result.chart.version, Some(String::from("1.2.3")));
+        assert_eq!(result.chart.namespace, String::from("test-namespace"));
+        assert_eq!(
+            result.chart.location.repo,
+            Some(String::from("https://test.repo"))
+        );
         assert_eq!(result.chart.location.local, None);
-
-        let temp_dir = std::env::temp_dir();
-        let temp_dir_name = temp_dir.file_name().unwrap().to_str().unwrap();
-        assert_eq!(result.release.name, temp_dir_name);
+        assert_eq!(result.release.name, "test-release");
 
         Ok(())
     }
@@ -195,33 +626,206 @@ mod tests {
     #[test]
     fn file_deserialized_duplicate_location_error() -> TestResult {
         // given
-        let mut deployment_file = Builder::new()
-            .prefix("deployment")
-            .suffix(".yaml")
-            .tempfile()?;
+        let root = tempfile::tempdir()?;
+        let dir = root.path().join("test-release");
+        std::fs::create_dir(&dir)?;
         let file_content = r#"
         name: TestName
-        version: TestVersion
-        namespace: TestNamespace
+        version: 1.2.3
+        namespace: test-namespace
         location:
-            repo: TestRepo
+            repo: https://test.repo
             local: TestPath
         "#;
-        writeln!(&mut deployment_file, "{file_content}")?;
-        let binding = deployment_file.into_temp_path();
-        let file_name = binding.file_name().unwrap().to_str().unwrap();
+        std::fs::write(dir.join("deployment.yaml"), file_content)?;
+
+        // when
+        let result = Deployment::all_in(&dir, &DeploymentFileName::default());
+
+        // then
+        match result.err() {
+            Some(crate::Error::InvalidDeployment(problems)) => {
+                assert_eq!(problems.len(), 1);
+                assert!(
+                    problems[0]
+                        .ends_with("location: exactly one of repo, oci or local must be set")
+                );
+            }
+            other => panic!("Expected invalid deployment error, got {other:?}"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn oci_location_appends_chart_name() {
+        // given
+        let chart = DeployChart::new(
+            String::from("nginx"),
+            None,
+            String::from("dev"),
+            ChartLocation::Oci(String::from("oci://registry.example.com/charts/")),
+        );
+        let mut command = Command::helm();
+
+        // when
+        chart.append_chart_location(&mut command);
+
+        // then
+        assert_eq!(
+            command.get_args(),
+            ["oci://registry.example.com/charts/nginx"]
+        );
+    }
+
+    #[test]
+    fn published_schema_up_to_date() -> TestResult {
+        // given
+        let published: serde_json::Value =
+            serde_json::from_str(include_str!("../schema/deployment.schema.json"))?;
+
+        // when
+        let result = super::schema();
+
+        // then
+        assert_eq!(
+            result, published,
+            "Regenerate the schema with: shippr schema > schema/deployment.schema.json"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn discover_nested_releases_within_depth() -> TestResult {
+        // given
+        let root = tempfile::tempdir()?;
+        std::fs::create_dir_all(root.path().join("direct"))?;
+        std::fs::write(root.path().join("direct/deployment.yaml"), VALID_DEPLOYMENT)?;
+        std::fs::create_dir_all(root.path().join("group/nested"))?;
+        std::fs::write(
+            root.path().join("group/nested/deployment.yaml"),
+            VALID_DEPLOYMENT,
+        )?;
 
         // when
-        let result = Deployment::new(
-            &std::env::temp_dir(),
-            Some(DeploymentFileName(file_name.to_string())),
+        let shallow = Deployment::discover(
+            root.path(),
+            &DeploymentFileName::default(),
+            &Discovery {
+                depth: 1,
+                strict: true,
+            },
+        )?;
+        let deep = Deployment::discover(
+            root.path(),
+            &DeploymentFileName::default(),
+            &Discovery {
+                depth: 2,
+                strict: true,
+            },
+        )?;
+
+        // then
+        let names: Vec<_> = shallow.iter().map(|d| d.release_name()).collect();
+        assert_eq!(names, ["direct"]);
+
+        let mut names: Vec<_> = deep.iter().map(|d| d.release_name()).collect();
+        names.sort();
+        assert_eq!(names, ["direct", "nested"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn variants_are_separate_releases() -> TestResult {
+        // given
+        let root = tempfile::tempdir()?;
+        let dir = root.path().join("shop");
+        std::fs::create_dir(&dir)?;
+        for file in [
+            "deployment.yaml",
+            "deployment.api.yaml",
+            "deployment.worker.toml",
+            "deployment.old.yaml",
+            "deployment.schema.json",
+            "deployment",
+            "values-default.api.yaml",
+            "values-default.worker.yml",
+        ] {
+            std::fs::write(dir.join(file), "")?;
+        }
+        std::fs::write(dir.join("deployment.yaml"), VALID_DEPLOYMENT)?;
+        std::fs::write(dir.join("deployment.api.yaml"), VALID_DEPLOYMENT)?;
+        std::fs::write(
+            dir.join("deployment.worker.toml"),
+            "name = \"TestName\"\nnamespace = \"test-namespace\"\nlocation = { local = \"chart\" }",
+        )?;
+
+        // when
+        let result = Deployment::all_in(&dir, &DeploymentFileName::default())?;
+
+        // then
+        let releases: Vec<_> = result
+            .iter()
+            .map(|d| (d.release_name(), d.variant()))
+            .collect();
+        assert_eq!(
+            releases,
+            [
+                ("shop-api", Some("api")),
+                ("shop-worker", Some("worker")),
+                ("shop", None),
+            ]
         );
 
+        Ok(())
+    }
+
+    #[test]
+    fn discover_strict_lists_all_invalid_files() -> TestResult {
+        // given
+        let root = tempfile::tempdir()?;
+        for name in ["valid", "broken1", "broken2"] {
+            std::fs::create_dir(root.path().join(name))?;
+        }
+        std::fs::write(root.path().join("valid/deployment.yaml"), VALID_DEPLOYMENT)?;
+        std::fs::write(root.path().join("broken1/deployment.yaml"), "name: [")?;
+        std::fs::write(
+            root.path().join("broken2/deployment.yaml"),
+            "name: NoNamespace",
+        )?;
+
+        // when
+        let strict = Deployment::discover(
+            root.path(),
+            &DeploymentFileName::default(),
+            &Discovery {
+                depth: 1,
+                strict: true,
+            },
+        );
+        let lenient = Deployment::discover(
+            root.path(),
+            &DeploymentFileName::default(),
+            &Discovery {
+                depth: 1,
+                strict: false,
+            },
+        )?;
+
         // then
-        assert!(matches!(
-            result.err(),
-            Some(crate::Error::DuplicateLocation)
-        ));
+        match strict.err() {
+            Some(crate::Error::InvalidDeploymentFiles(errors)) => {
+                assert_eq!(errors.len(), 2);
+                assert!(errors.iter().any(|e| e.contains("broken1")));
+                assert!(errors.iter().any(|e| e.contains("broken2")));
+            }
+            other => panic!("Expected invalid deployment files error, got {other:?}"),
+        }
+
+        let names: Vec<_> = lenient.iter().map(|d| d.release_name()).collect();
+        assert_eq!(names, ["valid"]);
 
         Ok(())
     }
@@ -229,6 +833,8 @@ mod tests {
 
 #[cfg(test)]
 pub mod test_fixtures {
+    use std::path::PathBuf;
+
     use super::{DeployChart, Deployment, Location, Release};
 
     pub fn deployment() -> Deployment {
@@ -236,14 +842,21 @@ pub mod test_fixtures {
             release: Release {
                 name: String::from("TestRelease"),
             },
+            dir: PathBuf::from("TestRelease"),
+            variant: None,
             chart: DeployChart {
                 name: String::from("TestChartName"),
                 version: Some(String::from("TestVersion")),
                 namespace: String::from("TestNamespace"),
                 location: Location {
                     repo: Some(String::from("TestRepo")),
-                    local: None,
+                    ..Default::default()
                 },
+                run_tests: None,
+                auto_rollback: None,
+                hooks: None,
+                suppress_policies: None,
+                suppress_lints: None,
             },
         }
     }
@@ -0,0 +1,556 @@
+use std::{collections::HashMap, path::Path};
+
+use semver::{Version, VersionReq};
+use serde_json::Value;
+use yaml_rust2::{
+    Event,
+    parser::{MarkedEventReceiver, Parser},
+    scanner::Marker,
+};
+
+use super::LOCATION_PLACEHOLDER;
+use crate::{lint::Lint, policy::Policy};
+
+const DEPLOYMENT_KEYS: &[&str] = &[
+    "name",
+    "version",
+    "namespace",
+    "location",
+    "runTests",
+    "autoRollback",
+    "hooks",
+    "suppressPolicies",
+    "suppressLints",
+];
+const LOCATION_KEYS: &[&str] = &["repo", "oci", "local"];
+const HOOK_KEYS: &[&str] = &["preDeploy", "postDeploy", "preUndeploy", "postUndeploy"];
+
+/// Kubernetes limits namespaces to DNS-1123 labels
+const MAX_NAMESPACE_LENGTH: usize = 63;
+/// Helm limits release names to 53 characters
+const MAX_RELEASE_NAME_LENGTH: usize = 53;
+
+/// Validates the content of a deployment file and returns every problem found.
+/// Problems contain the line and column of the affected key for YAML files
+pub(super) fn validate(file: &Path, content: &Value, release_name: &str) -> Vec<String> {
+    let positions = key_positions(file);
+    let mut problems = Problems {
+        file,
+        positions,
+        content: Vec::new(),
+    };
+
+    if !is_release_name(release_name) {
+        problems.push(
+            "",
+            format!(
+                "release name (directory name) '{release_name}' must be a lowercase DNS-1123 name with at most {MAX_RELEASE_NAME_LENGTH} characters"
+            ),
+        );
+    }
+
+    let Some(deployment) = content.as_object() else {
+        problems.push("", "must be a map of the deployment configuration");
+        return problems.content;
+    };
+    problems.unknown_keys("", deployment.keys(), DEPLOYMENT_KEYS);
+
+    match deployment.get("name") {
+        None => problems.push("", "name: is missing"),
+        Some(name) if scalar(name).is_some_and(|n| !n.is_empty()) => {}
+        Some(_) => problems.push("name", "must be the name of the chart"),
+    }
+
+    match deployment.get("namespace") {
+        None => problems.push("", "namespace: is missing"),
+        Some(namespace)
+            if scalar(namespace).is_some_and(|n| is_dns_label(&n, MAX_NAMESPACE_LENGTH)) => {}
+        Some(_) => problems.push(
+            "namespace",
+            format!(
+                "must be a lowercase DNS-1123 label with at most {MAX_NAMESPACE_LENGTH} characters"
+            ),
+        ),
+    }
+
+    match deployment.get("version") {
+        None | Some(Value::Null) => {}
+        Some(version) if scalar(version).is_some_and(|v| is_version(&v)) => {}
+        Some(_) => problems.push(
+            "version",
+            "must be a semantic version (e.g. 1.2.3) or a version range (e.g. ^1.2 or >=1.0 <2.0)",
+        ),
+    }
+
+    for key in ["runTests", "autoRollback"] {
+        match deployment.get(key) {
+            None | Some(Value::Null) | Some(Value::Bool(_)) => {}
+            Some(Value::String(s)) if s == "true" || s == "false" => {}
+            Some(_) => problems.push(key, "must be true or false"),
+        }
+    }
+
+    match deployment.get("hooks") {
+        None | Some(Value::Null) => {}
+        Some(Value::Object(hooks)) => {
+            problems.unknown_keys("hooks", hooks.keys(), HOOK_KEYS);
+            for key in HOOK_KEYS {
+                match hooks.get(*key) {
+                    None | Some(Value::Null) => {}
+                    Some(Value::Array(commands))
+                        if commands
+                            .iter()
+                            .all(|c| c.as_str().is_some_and(|c| !c.trim().is_empty())) => {}
+                    Some(_) => problems.push(&format!("hooks.{key}"), "must be a list of commands"),
+                }
+            }
+        }
+        Some(_) => problems.push("hooks", "must be a map of hook commands"),
+    }
+
+    match deployment.get("suppressPolicies") {
+        None | Some(Value::Null) => {}
+        Some(Value::Array(policies))
+            if policies.iter().all(|p| {
+                p.as_str()
+                    .is_some_and(|p| Policy::ALL.iter().any(|policy| policy.to_string() == p))
+            }) => {}
+        Some(_) => problems.push(
+            "suppressPolicies",
+            format!(
+                "must be a list of policies. Expected any of: {}",
+                Policy::ALL.map(|policy| policy.to_string()).join(", ")
+            ),
+        ),
+    }
+
+    match deployment.get("suppressLints") {
+        None | Some(Value::Null) => {}
+        Some(Value::Array(lints))
+            if lints.iter().all(|l| {
+                l.as_str()
+                    .is_some_and(|l| Lint::ALL.iter().any(|lint| lint.to_string() == l))
+            }) => {}
+        Some(_) => problems.push(
+            "suppressLints",
+            format!(
+                "must be a list of lints. Expected any of: {}",
+                Lint::ALL.map(|lint| lint.to_string()).join(", ")
+            ),
+        ),
+    }
+
+    match deployment.get("location") {
+        None => problems.push("", "location: is missing"),
+        Some(Value::Object(location)) => {
+            problems.unknown_keys("location", location.keys(), LOCATION_KEYS);
+
+            let set = LOCATION_KEYS
+                .iter()
+                .filter(|key| location.get(**key).is_some_and(|v| !v.is_null()))
+                .count();
+            if set != 1 {
+                problems.push("location", "exactly one of repo, oci or local must be set");
+            }
+
+            match location.get("repo") {
+                None | Some(Value::Null) => {}
+                Some(Value::String(repo)) if repo == LOCATION_PLACEHOLDER => problems.push(
+                    "location.repo",
+                    "is a placeholder written by import. Replace it with the URL of the chart repository",
+                ),
+                Some(repo) if scalar(repo).is_some_and(|r| is_repository_url(&r)) => {}
+                Some(_) => problems.push(
+                    "location.repo",
+                    "must be a http(s) URL of a chart repository or a configured repository alias",
+                ),
+            }
+
+            match location.get("oci") {
+                None | Some(Value::Null) => {}
+                Some(oci) if scalar(oci).is_some_and(|o| o.starts_with("oci://")) => {}
+                Some(_) => problems.push(
+                    "location.oci",
+                    "must be an oci:// reference of a chart registry",
+                ),
+            }
+
+            match location.get("local") {
+                None | Some(Value::Null) => {}
+                Some(local) if scalar(local).is_some_and(|l| !l.is_empty()) => {}
+                Some(_) => problems.push("location.local", "must be the path of the chart"),
+            }
+        }
+        Some(_) => problems.push("location", "must be a map with one of repo, oci or local"),
+    }
+
+    problems.content
+}
+
+struct Problems<'a> {
+    file: &'a Path,
+    positions: HashMap<String, Marker>,
+    content: Vec<String>,
+}
+
+impl Problems<'_> {
+    /// Adds a problem for the key. The key is a dot separated path, empty for the whole file
+    fn push<M: AsRef<str>>(&mut self, key: &str, message: M) {
+        let message = message.as_ref();
+        let file = self.file.display();
+        let problem = match self.positions.get(key) {
+            Some(marker) => format!(
+                "{file}:{}:{}: {key}: {message}",
+                marker.line(),
+                marker.col() + 1
+            ),
+            None if key.is_empty() => format!("{file}: {message}"),
+            None => format!("{file}: {key}: {message}"),
+        };
+
+        self.content.push(problem);
+    }
+
+    fn unknown_keys<'k, I>(&mut self, parent: &str, keys: I, known: &[&str])
+    where
+        I: Iterator<Item = &'k String>,
+    {
+        for key in keys.filter(|key| !known.contains(&key.as_str())) {
+            let path = join(parent, key);
+            self.push(
+                &path,
+                format!("unknown key. Expected one of: {}", known.join(", ")),
+            );
+        }
+    }
+}
+
+fn join(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{parent}.{key}")
+    }
+}
+
+/// Strings and numbers are accepted, as YAML reads a version like 1.0 as a number
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn is_dns_label(value: &str, max_length: usize) -> bool {
+    !value.is_empty()
+        && value.len() <= max_length
+        && value
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !value.starts_with('-')
+        && !value.ends_with('-')
+}
+
+fn is_repository_url(value: &str) -> bool {
+    value.starts_with("http://") || value.starts_with("https://")
+}
+
+fn is_release_name(value: &str) -> bool {
+    value.len() <= MAX_RELEASE_NAME_LENGTH
+        && value
+            .split('.')
+            .all(|label| is_dns_label(label, MAX_RELEASE_NAME_LENGTH))
+}
+
+/// Accepts versions and the version ranges helm understands, e.g. `>=1.0 <2.0 || ^3`
+fn is_version(value: &str) -> bool {
+    value.split("||").all(|alternative| {
+        let tokens: Vec<_> = alternative
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|t| !t.is_empty())
+            .collect();
+
+        let requirement = match tokens.as_slice() {
+            [] => return false,
+            [version] => return is_single_version(version),
+            [from, "-", to] => format!(">={from}, <={to}"),
+            _ => {
+                let mut comparators: Vec<String> = Vec::new();
+                let mut operator = String::new();
+                for token in tokens {
+                    if token.chars().all(|c| "<>=~^!".contains(c)) {
+                        operator.push_str(token);
+                    } else {
+                        comparators.push(format!("{operator}{token}"));
+                        operator.clear();
+                    }
+                }
+                comparators.join(", ")
+            }
+        };
+
+        VersionReq::parse(&requirement).is_ok()
+    })
+}
+
+fn is_single_version(value: &str) -> bool {
+    let value = value.strip_prefix('v').unwrap_or(value);
+    Version::parse(value).is_ok() || VersionReq::parse(value).is_ok()
+}
+
+/// Finds the line and column of every key of a YAML file.
+/// Other formats are not supported and return no positions
+fn key_positions(file: &Path) -> HashMap<String, Marker> {
+    let is_yaml = file
+        .extension()
+        .is_some_and(|ext| ext == "yaml" || ext == "yml");
+    let Some(content) = is_yaml
+        .then(|| std::fs::read_to_string(file).ok())
+        .flatten()
+    else {
+        return HashMap::new();
+    };
+
+    let mut receiver = KeyPositions::default();
+    // Syntax errors are reported by the config parser
+    let _ = Parser::new_from_str(&content).load(&mut receiver, false);
+    receiver.positions
+}
+
+#[derive(Default)]
+struct KeyPositions {
+    stack: Vec<Node>,
+    positions: HashMap<String, Marker>,
+}
+
+enum Node {
+    Mapping { path: String, key: Option<String> },
+    Sequence,
+}
+
+impl KeyPositions {
+    /// Path of the value that is currently being parsed
+    fn value_path(&self) -> String {
+        match self.stack.last() {
+            Some(Node::Mapping {
+                path,
+                key: Some(key),
+            }) => join(path, key),
+            _ => String::new(),
+        }
+    }
+
+    /// Marks the current value as finished, so the next scalar of a mapping is a key again
+    fn finish_value(&mut self) {
+        if let Some(Node::Mapping { key, .. }) = self.stack.last_mut() {
+            *key = None;
+        }
+    }
+}
+
+impl MarkedEventReceiver for KeyPositions {
+    fn on_event(&mut self, event: Event, marker: Marker) {
+        match event {
+            Event::MappingStart(..) => {
+                let path = self.value_path();
+                self.stack.push(Node::Mapping { path, key: None });
+            }
+            Event::SequenceStart(..) => self.stack.push(Node::Sequence),
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.stack.pop();
+                self.finish_value();
+            }
+            Event::Scalar(value, ..) => match self.stack.last_mut() {
+                Some(Node::Mapping {
+                    path,
+                    key: key @ None,
+                }) => {
+                    self.positions.insert(join(path, &value), marker);
+                    *key = Some(value);
+                }
+                _ => self.finish_value(),
+            },
+            Event::Alias(_) => self.finish_value(),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use rstest::rstest;
+    use serde_json::json;
+
+    use super::{LOCATION_PLACEHOLDER, is_version, validate};
+
+    type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+    #[rstest]
+    #[case("1.2.3")]
+    #[case("v1.2.3")]
+    #[case("1.0")]
+    #[case("^1.2")]
+    #[case("~1.2.3")]
+    #[case("1.2.x")]
+    #[case(">=1.0 <2.0")]
+    #[case(">= 1.0, < 2.0")]
+    #[case("1.0 - 1.4")]
+    #[case("^1.0 || ^2.0")]
+    fn valid_versions(#[case] version: &str) {
+        assert!(is_version(version), "{version} should be valid");
+    }
+
+    #[rstest]
+    #[case("latest")]
+    #[case("TestVersion")]
+    #[case("1.2.3.4")]
+    #[case("")]
+    #[case("^1.0 ||")]
+    fn invalid_versions(#[case] version: &str) {
+        assert!(!is_version(version), "{version} should be invalid");
+    }
+
+    #[test]
+    fn report_every_problem_with_position() -> TestResult {
+        // given
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("deployment.yaml");
+        let file_content = "name: nginx
+version: latest
+namespace: Dev
+location:
+  repo: https://charts.example.com
+  local: /charts/nginx
+imageName: nginx:latest
+";
+        std::fs::write(&file, file_content)?;
+        let content = json!({
+            "name": "nginx",
+            "version": "latest",
+            "namespace": "Dev",
+            "location": {
+                "repo": "https://charts.example.com",
+                "local": "/charts/nginx",
+            },
+            "imageName": "nginx:latest",
+        });
+
+        // when
+        let result = validate(&file, &content, "nginx-release");
+
+        // then
+        let file = file.display();
+        assert_eq!(result.len(), 4);
+        assert!(result.contains(&format!(
+            "{file}:7:1: imageName: unknown key. Expected one of: name, version, namespace, location, runTests, autoRollback, hooks, suppressPolicies, suppressLints"
+        )));
+        assert!(
+            result
+                .iter()
+                .any(|p| p.starts_with(&format!("{file}:3:1: namespace:")))
+        );
+        assert!(
+            result
+                .iter()
+                .any(|p| p.starts_with(&format!("{file}:2:1: version:")))
+        );
+        assert!(result.contains(&format!(
+            "{file}:4:1: location: exactly one of repo, oci or local must be set"
+        )));
+
+        Ok(())
+    }
+
+    #[test]
+    fn report_missing_keys_and_release_name() {
+        // given
+        let file = Path::new("deployment.toml");
+        let content = json!({
+            "location": {
+                "git": "https://git.example.com/charts",
+            },
+            "runTests": "yes",
+            "hooks": {
+                "preDeploy": "./migrate.sh",
+                "onFailure": ["./notify.sh"],
+            },
+            "suppressPolicies": ["latest-tag"],
+            "suppressLints": "unknown_key",
+        });
+
+        // when
+        let result = validate(file, &content, "Nginx_Release");
+
+        // then
+        assert_eq!(
+            result,
+            [
+                "deployment.toml: release name (directory name) 'Nginx_Release' must be a lowercase DNS-1123 name with at most 53 characters",
+                "deployment.toml: name: is missing",
+                "deployment.toml: namespace: is missing",
+                "deployment.toml: runTests: must be true or false",
+                "deployment.toml: hooks.onFailure: unknown key. Expected one of: preDeploy, postDeploy, preUndeploy, postUndeploy",
+                "deployment.toml: hooks.preDeploy: must be a list of commands",
+                "deployment.toml: suppressPolicies: must be a list of policies. Expected any of: latest_tag, resources, privileged, required_labels, namespace",
+                "deployment.toml: suppressLints: must be a list of lints. Expected any of: unknown_key, type_mismatch, repeated_default",
+                "deployment.toml: location.git: unknown key. Expected one of: repo, oci, local",
+                "deployment.toml: location: exactly one of repo, oci or local must be set",
+            ]
+        );
+    }
+
+    #[test]
+    fn import_placeholder_reported() {
+        // given
+        let file = Path::new("deployment.yaml");
+        let content = json!({
+            "name": "nginx",
+            "namespace": "dev",
+            "location": {
+                "repo": LOCATION_PLACEHOLDER,
+            },
+        });
+
+        // when
+        let result = validate(file, &content, "nginx");
+
+        // then
+        assert_eq!(
+            result,
+            [
+                "deployment.yaml: location.repo: is a placeholder written by import. Replace it with the URL of the chart repository",
+            ]
+        );
+    }
+
+    #[test]
+    fn valid_deployment_no_problems() {
+        // given
+        let file = Path::new("deployment.json");
+        let content = json!({
+            "name": "nginx",
+            "version": 1.0,
+            "namespace": "dev",
+            "location": {
+                "local": "/charts/nginx",
+            },
+            "runTests": true,
+            "autoRollback": "false",
+            "hooks": {
+                "preDeploy": ["kubectl apply -f crds"],
+                "postDeploy": ["./notify.sh"],
+            },
+            "suppressPolicies": ["latest_tag", "resources"],
+            "suppressLints": ["unknown_key"],
+        });
+
+        // when
+        let result = validate(file, &content, "nginx-release");
+
+        // then
+        assert!(result.is_empty(), "{result:?}");
+    }
+}
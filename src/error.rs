@@ -10,8 +10,11 @@ pub enum Error {
     #[error("Either --namespace or --all-namespaces must be present")]
     NoNamespacePassed,
 
-    #[error("Both the local and repository location are set. Only one can be set")]
-    DuplicateLocation,
+    #[error("No deployment file found: path: {0}")]
+    DeploymentFileMissing(PathBuf),
+
+    #[error("The deployment file is invalid:\n{}", .0.join("\n"))]
+    InvalidDeployment(Vec<String>),
 
     #[error("{0} is not installed")]
     MissingTool(String),
@@ -19,12 +22,24 @@ pub enum Error {
     #[error("{0} is installed but is not working properly")]
     FaultyTool(String),
 
-    #[error("The values-default.yaml file does not exist: path: {0}")]
+    #[error("{0} {1} is too old. shippr needs at least version {2}")]
+    OutdatedTool(String, String, String),
+
+    #[error("The values-default file does not exist: path: {0}")]
     ValuesDefaultMissing(PathBuf),
 
     #[error("A profile was passed but no fitting values file exists: path: {0}")]
     ValuesProfileMissing(PathBuf),
 
+    #[error("Found the same values file in multiple formats. Only one may exist:\n{}", .0.join("\n"))]
+    AmbiguousValuesFiles(Vec<String>),
+
+    #[error("No chart location given. Pass one of --repo, --oci or --local")]
+    MissingChartLocation,
+
+    #[error("The given profile name cannot be a path. As such it cannot contain characters like /")]
+    ProfileIsPath,
+
     #[error("The release name could not be determined in that namespace")]
     CouldNotFigureOutReleaseName,
 
@@ -33,6 +48,67 @@ pub enum Error {
     )]
     ReleaseNameIsPath,
 
+    #[error("Found invalid deployment files:\n{}", .0.join("\n"))]
+    InvalidDeploymentFiles(Vec<String>),
+
+    #[error("Refusing to overwrite existing directories:\n{}", .0.join("\n"))]
+    ImportTargetsExist(Vec<String>),
+
+    #[error("Releases in the namespace {0} are protected and cannot be undeployed")]
+    ProtectedNamespace(String),
+
+    #[error("The release {0} is unhealthy:\n{problems}", problems = .1.join("\n"))]
+    UnhealthyRelease(String, Vec<String>),
+
+    #[error("The check of {0} failed:\n{1}")]
+    CheckFailed(String, String),
+
+    #[error("{0} of {1} checks failed")]
+    ChecksFailed(usize, usize),
+
+    #[error("The release {0} violates policies:\n{violations}", violations = .1.join("\n"))]
+    PolicyViolations(String, Vec<String>),
+
+    #[error("The values files of {0} do not match the chart:\n{findings}", findings = .1.join("\n"))]
+    InvalidValues(String, Vec<String>),
+
+    #[error(
+        "No chart directory found for {0}. Local charts must be a directory, not a packaged chart"
+    )]
+    UnreadableChart(String),
+
+    #[error("Not everything succeeded:\n{}", .0.join("\n"))]
+    Unsuccessful(Vec<String>),
+
+    #[error("The deploy of {0} failed: {1}")]
+    DeployFailed(String, String),
+
+    #[error("The history of {0} cannot be read: {1}")]
+    HistoryUnavailable(String, String),
+
+    #[error("The status of {0} cannot be read: {1}")]
+    StatusUnavailable(String, String),
+
+    #[error("Could not restore {0}. It is in an unknown state and has to be fixed manually: {1}")]
+    RestoreFailed(String, String),
+
+    #[error("The tests of the following releases failed: {}", .0.join(", "))]
+    TestsFailed(Vec<String>),
+
+    #[error("The {0} hook of {1} failed: {2}")]
+    HookFailed(String, String, String),
+
+    #[error("The release {0} is locked by {1}. Retry later or pass --wait-for-lock")]
+    ReleaseLocked(String, String),
+
+    #[error(
+        "'{0}' is not a point in time. Use a timestamp (2026-10-01T12:00:00Z), a date (2026-10-01) or a duration before now (7d)"
+    )]
+    InvalidTime(String),
+
+    #[error("The command failed with exit code {1:?}: {0}")]
+    CommandFailed(String, Option<i32>),
+
     // external errors
     #[error("Configuration Error: {0}")]
     ConfigError(#[from] config::ConfigError),
@@ -42,6 +118,15 @@ pub enum Error {
 
     #[error("Failed to convert CLI stdout to string: {0}")]
     Utf8Error(#[from] FromUtf8Error),
+
+    #[error("JSON Error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("YAML Error: {0}")]
+    YamlError(String),
+
+    #[error("TOML Error: {0}")]
+    TomlError(String),
 }
 
 impl fmt::Debug for Error {
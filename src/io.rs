@@ -1,5 +1,5 @@
-use crate::Result;
-use std::io;
+use crate::{Result, output::message};
+use std::io::{self, IsTerminal};
 
 /// Asks the user for an input and returns the given answer
 pub(crate) fn user_confirmation(prompt: &str) -> Result<bool> {
@@ -13,7 +13,7 @@ where
 {
     let approvals = ["y", "yes"];
 
-    println!("{prompt}");
+    message!("{prompt}");
 
     let mut input = String::new();
     reader.read_line(&mut input)?;
@@ -22,6 +22,37 @@ where
     Ok(approvals.contains(&input.as_str()))
 }
 
+/// Checks if a user can answer prompts
+pub(crate) fn is_interactive() -> bool {
+    io::stdin().is_terminal()
+}
+
+/// Asks the user for an input and returns the trimmed answer.
+/// Returns the default if nothing was entered
+pub(crate) fn user_input(prompt: &str, default: Option<&str>) -> Result<String> {
+    internal_user_input(prompt, default, io::stdin().lock())
+}
+
+/// Testable internal interface
+fn internal_user_input<R>(prompt: &str, default: Option<&str>, mut reader: R) -> Result<String>
+where
+    R: io::BufRead,
+{
+    match default {
+        Some(default) => message!("{prompt} [{default}]"),
+        None => message!("{prompt}"),
+    }
+
+    let mut input = String::new();
+    reader.read_line(&mut input)?;
+    let input = input.trim();
+
+    match default {
+        Some(default) if input.is_empty() => Ok(default.to_string()),
+        _ => Ok(input.to_string()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::error::Error;
@@ -64,4 +95,30 @@ mod tests {
 
         Ok(())
     }
+
+    #[rstest]
+    #[case(b"\n", "default")]
+    #[case(b"  \n", "default")]
+    #[case(b"answer\n", "answer")]
+    #[case(b" answer \n", "answer")]
+    fn input_with_default(#[case] input: &[u8], #[case] expected: &str) -> TestResult {
+        // when
+        let result = internal_user_input("Unimportant prompt", Some("default"), input)?;
+
+        // then
+        assert_eq!(result, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn input_without_default_empty() -> TestResult {
+        // when
+        let result = internal_user_input("Unimportant prompt", None, &b"\n"[..])?;
+
+        // then
+        assert_eq!(result, "");
+
+        Ok(())
+    }
 }
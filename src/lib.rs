@@ -3,8 +3,15 @@ mod error;
 pub(crate) mod command;
 pub(crate) mod deploy_config;
 pub(crate) mod io;
+pub(crate) mod yaml;
 
 pub mod actions;
+pub mod audit;
+pub mod lint;
+pub mod output;
+pub mod policy;
+pub mod settings;
+pub mod tools;
 
 // Re-Exports
 pub use error::Error;
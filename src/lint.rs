@@ -0,0 +1,534 @@
+use std::fmt;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use yaml_rust2::{Yaml, yaml::Hash};
+
+use crate::policy::Severity;
+
+/// Problems of values files, found by comparing them with the chart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Lint {
+    /// Neither the values nor the schema of the chart have the key. Helm ignores it
+    UnknownKey,
+    /// The value has another type than the schema or the default of the chart
+    TypeMismatch,
+    /// A profile sets a key to the value it already has without the profile
+    RepeatedDefault,
+}
+
+impl Lint {
+    pub(crate) const ALL: [Lint; 3] = [Lint::UnknownKey, Lint::TypeMismatch, Lint::RepeatedDefault];
+}
+
+impl fmt::Display for Lint {
+    /// Named like the key in the settings
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Lint::UnknownKey => "unknown_key",
+            Lint::TypeMismatch => "type_mismatch",
+            Lint::RepeatedDefault => "repeated_default",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Severity of every lint of lint-values and check
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LintSettings {
+    pub unknown_key: Severity,
+    pub type_mismatch: Severity,
+    pub repeated_default: Severity,
+}
+
+impl Default for LintSettings {
+    fn default() -> Self {
+        Self {
+            unknown_key: Severity::Error,
+            type_mismatch: Severity::Error,
+            repeated_default: Severity::Warning,
+        }
+    }
+}
+
+impl LintSettings {
+    fn severity(&self, lint: Lint) -> Severity {
+        match lint {
+            Lint::UnknownKey => self.unknown_key,
+            Lint::TypeMismatch => self.type_mismatch,
+            Lint::RepeatedDefault => self.repeated_default,
+        }
+    }
+}
+
+/// Collects the findings of the lints that are reported for the release
+struct Findings<'a> {
+    settings: &'a LintSettings,
+    suppressed: &'a [Lint],
+    found: Vec<Finding>,
+}
+
+impl Findings<'_> {
+    /// Skips lints that are off or suppressed for the release
+    fn push(&mut self, lint: Lint, file: &str, key: &str, details: &str) {
+        let severity = self.settings.severity(lint);
+        if severity == Severity::Off || self.suppressed.contains(&lint) {
+            return;
+        }
+        self.found.push(Finding {
+            lint,
+            severity,
+            file: file.to_string(),
+            key: key.to_string(),
+            details: details.to_string(),
+        });
+    }
+}
+
+/// A key of a values file with a problem
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct Finding {
+    pub(crate) lint: Lint,
+    pub(crate) severity: Severity,
+    /// Name of the values file, e.g. values-prod.yaml
+    pub(crate) file: String,
+    /// Path of the key, e.g. image.tag
+    pub(crate) key: String,
+    pub(crate) details: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}: {} {} ({})",
+            self.severity, self.file, self.key, self.details, self.lint
+        )
+    }
+}
+
+/// Everything of a chart that decides which values it accepts
+#[derive(Debug)]
+pub(crate) struct ChartValues {
+    /// Content of values.yaml
+    pub(crate) values: Yaml,
+    /// Content of values.schema.json
+    pub(crate) schema: Option<Value>,
+    /// Names and aliases of the dependencies. Their values are passed on to the subcharts
+    pub(crate) dependencies: Vec<String>,
+}
+
+/// Lints values-default and the profiles against the chart.
+/// Profiles are also compared with the values they would have without them.
+/// Lints that are off in the settings or suppressed for the release are skipped
+pub(crate) fn lint(
+    chart: &ChartValues,
+    default: (&str, &Yaml),
+    profiles: &[(&str, &Yaml)],
+    settings: &LintSettings,
+    suppressed: &[Lint],
+) -> Vec<Finding> {
+    let mut findings = Findings {
+        settings,
+        suppressed,
+        found: Vec::new(),
+    };
+    let (file, values) = default;
+    check_keys(chart, file, values, &mut findings);
+
+    let defaults = merge(&chart.values, values);
+    for (file, values) in profiles {
+        check_keys(chart, file, values, &mut findings);
+        check_repeated(file, "", values, &defaults, &mut findings);
+    }
+
+    findings.found
+}
+
+fn check_keys(chart: &ChartValues, file: &str, values: &Yaml, findings: &mut Findings) {
+    let Yaml::Hash(values) = values else {
+        return;
+    };
+
+    for (key, value) in values {
+        let Some(key) = key.as_str() else {
+            continue;
+        };
+        // Global values are shared with every subchart, the values of a subchart are checked by it
+        if key == "global" || chart.dependencies.iter().any(|name| name == key) {
+            continue;
+        }
+        walk(
+            file,
+            key,
+            key,
+            value,
+            &chart.values,
+            chart.schema.as_ref(),
+            findings,
+        );
+    }
+}
+
+/// Checks the key and everything below it against the default and schema of its parent
+fn walk(
+    file: &str,
+    path: &str,
+    key: &str,
+    value: &Yaml,
+    parent_default: &Yaml,
+    parent_schema: Option<&Value>,
+    findings: &mut Findings,
+) {
+    // null removes a key in helm, which is never a problem
+    if value.is_null() {
+        return;
+    }
+    let default = &parent_default[key];
+    let schema = parent_schema.and_then(|schema| schema.get("properties")?.get(key));
+
+    if default.is_badvalue() && schema.is_none() {
+        if !accepts_any_key(parent_default, parent_schema) {
+            findings.push(Lint::UnknownKey, file, path, "does not exist in the chart");
+        }
+        return;
+    }
+
+    if let Some(expected) = expected_type(value, default, schema) {
+        let details = format!(
+            "is of type {}, but the chart expects {expected}",
+            type_of(value)
+        );
+        findings.push(Lint::TypeMismatch, file, path, &details);
+        return;
+    }
+
+    if let Yaml::Hash(children) = value {
+        for (child, value) in children {
+            let Some(child) = child.as_str() else {
+                continue;
+            };
+            let path = format!("{path}.{child}");
+            walk(file, &path, child, value, default, schema, findings);
+        }
+    }
+}
+
+/// Maps the chart leaves empty, like annotations, take any key.
+/// A schema decides by its additional properties, or if it lists no properties
+fn accepts_any_key(default: &Yaml, schema: Option<&Value>) -> bool {
+    if let Some(schema) = schema {
+        match schema.get("additionalProperties") {
+            Some(Value::Bool(additional)) => return *additional,
+            Some(_) => return true,
+            None if schema.get("patternProperties").is_some() => return true,
+            None if schema.get("properties").is_some() => return false,
+            None => {}
+        }
+    }
+
+    match default {
+        Yaml::Hash(default) => default.is_empty(),
+        Yaml::Null | Yaml::BadValue => true,
+        _ => false,
+    }
+}
+
+/// The types the chart expects, if the value has none of them.
+/// The schema takes precedence over the type of the default
+fn expected_type(value: &Yaml, default: &Yaml, schema: Option<&Value>) -> Option<String> {
+    let actual = type_of(value);
+    let expected: Vec<&str> = match schema.and_then(|schema| schema.get("type")) {
+        Some(Value::String(expected)) => vec![expected.as_str()],
+        Some(Value::Array(expected)) => expected.iter().filter_map(Value::as_str).collect(),
+        _ if default.is_null() || default.is_badvalue() => return None,
+        // Whole numbers in the defaults do not mean floats are not accepted
+        _ => match type_of(default) {
+            "integer" => vec!["number"],
+            default => vec![default],
+        },
+    };
+
+    let matches = expected
+        .iter()
+        .any(|expected| *expected == actual || (*expected == "number" && actual == "integer"));
+    (!matches).then(|| expected.join(" or "))
+}
+
+/// Type of the value, named like in a JSON schema
+fn type_of(value: &Yaml) -> &'static str {
+    match value {
+        Yaml::Real(_) => "number",
+        Yaml::Integer(_) => "integer",
+        Yaml::String(_) => "string",
+        Yaml::Boolean(_) => "boolean",
+        Yaml::Array(_) => "array",
+        Yaml::Hash(_) => "object",
+        Yaml::Alias(_) | Yaml::Null | Yaml::BadValue => "null",
+    }
+}
+
+fn check_repeated(
+    file: &str,
+    prefix: &str,
+    values: &Yaml,
+    defaults: &Yaml,
+    findings: &mut Findings,
+) {
+    let Yaml::Hash(values) = values else {
+        return;
+    };
+
+    for (key, value) in values {
+        let Some(key) = key.as_str() else {
+            continue;
+        };
+        let path = if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        let default = &defaults[key];
+
+        if value.as_hash().is_some() && default.as_hash().is_some() {
+            check_repeated(file, &path, value, default, findings);
+        } else if value == default {
+            findings.push(
+                Lint::RepeatedDefault,
+                file,
+                &path,
+                "repeats the default value",
+            );
+        }
+    }
+}
+
+/// Merges the values like helm: maps are merged, null removes a key and everything else is replaced
+fn merge(base: &Yaml, values: &Yaml) -> Yaml {
+    let (Yaml::Hash(base), Yaml::Hash(values)) = (base, values) else {
+        return values.clone();
+    };
+
+    let mut merged: Hash = base.clone();
+    for (key, value) in values {
+        if value.is_null() {
+            merged.remove(key);
+            continue;
+        }
+        let value = match merged.get(key) {
+            Some(current) => merge(current, value),
+            None => value.clone(),
+        };
+        merged.insert(key.clone(), value);
+    }
+
+    Yaml::Hash(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use yaml_rust2::{Yaml, YamlLoader};
+
+    use super::{ChartValues, Lint, LintSettings, lint};
+    use crate::policy::Severity;
+
+    type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+    const CHART: &str = "
+replicaCount: 1
+image:
+  repository: nginx
+  tag: \"\"
+podAnnotations: {}
+resources: {}
+affinity:
+";
+
+    fn load(values: &str) -> Result<Yaml, Box<dyn std::error::Error>> {
+        Ok(YamlLoader::load_from_str(values)?.remove(0))
+    }
+
+    fn chart() -> Result<ChartValues, Box<dyn std::error::Error>> {
+        Ok(ChartValues {
+            values: load(CHART)?,
+            schema: None,
+            dependencies: vec![String::from("redis")],
+        })
+    }
+
+    #[test]
+    fn unknown_keys_and_type_mismatches() -> TestResult {
+        // given
+        let chart = chart()?;
+        let default = load(
+            "
+replicaCont: 2
+image:
+  tag: 1.27
+  pullPolicy: Always
+podAnnotations:
+  prometheus.io/scrape: \"true\"
+affinity:
+  nodeAffinity: {}
+global:
+  imageRegistry: registry.example.com
+redis:
+  enabled: true
+",
+        )?;
+
+        // when
+        let result = lint(
+            &chart,
+            ("values-default.yaml", &default),
+            &[],
+            &LintSettings::default(),
+            &[],
+        );
+
+        // then
+        let result: Vec<_> = result.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            result,
+            [
+                "error: values-default.yaml: replicaCont does not exist in the chart (unknown_key)",
+                "error: values-default.yaml: image.tag is of type number, but the chart expects string (type_mismatch)",
+                "error: values-default.yaml: image.pullPolicy does not exist in the chart (unknown_key)",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn schema_decides_keys_and_types() -> TestResult {
+        // given
+        let chart = ChartValues {
+            schema: Some(json!({
+                "properties": {
+                    "replicaCount": { "type": "integer" },
+                    "resources": {
+                        "type": "object",
+                        "properties": { "limits": { "type": "object" } },
+                    },
+                    "extraEnv": { "type": ["array", "null"] },
+                    "labels": { "type": "object", "additionalProperties": { "type": "string" } },
+                }
+            })),
+            ..chart()?
+        };
+        let default = load(
+            "
+replicaCount: \"2\"
+resources:
+  limit: {}
+extraEnv: []
+labels:
+  team: web
+",
+        )?;
+
+        // when
+        let result = lint(
+            &chart,
+            ("values-default.yaml", &default),
+            &[],
+            &LintSettings::default(),
+            &[],
+        );
+
+        // then
+        let result: Vec<_> = result
+            .iter()
+            .map(|finding| (finding.lint, finding.key.as_str()))
+            .collect();
+        assert_eq!(
+            result,
+            [
+                (Lint::TypeMismatch, "replicaCount"),
+                (Lint::UnknownKey, "resources.limit"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn configured_and_suppressed_lints() -> TestResult {
+        // given
+        let chart = chart()?;
+        let default = load("replicaCont: 2\nimage:\n  tag: 1.27")?;
+        let profile = load("replicaCount: 1")?;
+        let settings = LintSettings {
+            unknown_key: Severity::Warning,
+            repeated_default: Severity::Off,
+            ..LintSettings::default()
+        };
+
+        // when
+        let configured = lint(
+            &chart,
+            ("values-default.yaml", &default),
+            &[("values-prod.yaml", &profile)],
+            &settings,
+            &[],
+        );
+        let suppressed = lint(
+            &chart,
+            ("values-default.yaml", &default),
+            &[("values-prod.yaml", &profile)],
+            &settings,
+            &[Lint::TypeMismatch],
+        );
+
+        // then
+        let configured: Vec<_> = configured
+            .iter()
+            .map(|finding| (finding.lint, finding.severity))
+            .collect();
+        assert_eq!(
+            configured,
+            [
+                (Lint::UnknownKey, Severity::Warning),
+                (Lint::TypeMismatch, Severity::Error),
+            ]
+        );
+        let suppressed: Vec<_> = suppressed.iter().map(|finding| finding.lint).collect();
+        assert_eq!(suppressed, [Lint::UnknownKey]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn profile_repeating_defaults() -> TestResult {
+        // given
+        let chart = chart()?;
+        let default = load("replicaCount: 2\nimage:\n  tag: \"1.27\"")?;
+        let profile = load("replicaCount: 2\nimage:\n  repository: nginx\n  tag: \"1.28\"")?;
+
+        // when
+        let result = lint(
+            &chart,
+            ("values-default.yaml", &default),
+            &[("values-prod.yaml", &profile)],
+            &LintSettings::default(),
+            &[],
+        );
+
+        // then
+        let result: Vec<_> = result.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            result,
+            [
+                "warning: values-prod.yaml: replicaCount repeats the default value (repeated_default)",
+                "warning: values-prod.yaml: image.repository repeats the default value (repeated_default)",
+            ]
+        );
+
+        Ok(())
+    }
+}
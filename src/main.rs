@@ -1,7 +1,22 @@
-use std::{error::Error, path::PathBuf, process};
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, Instant},
+};
 
 use clap::{ArgAction, Args, Parser, Subcommand};
-use tracing::error;
+use jiff::Timestamp;
+use shippr::{
+    actions::{ChartLocation, CheckOptions, DeployOptions, DeploymentFormat, InitOptions},
+    audit::AuditQuery,
+    output::{self, OutputFormat},
+    settings::{self, Settings},
+    tools::Tool,
+};
+
+/// Exit code of a dry-run that found releases which would be removed
+const DRY_RUN_CHANGES_EXIT_CODE: u8 = 3;
 
 #[derive(Debug, Parser)]
 #[clap(version)]
@@ -19,22 +34,75 @@ struct App {
     )]
     verbose: u8,
 
+    /// Output format. With json a single JSON document is written to stdout
+    /// and all other messages are written to stderr.
+    /// [Default: output of the settings, or text]
+    #[arg(global = true, long, short = 'o', value_enum)]
+    output: Option<OutputFormat>,
+
     #[command(subcommand)]
     command: Command,
 }
 
 #[derive(Debug, Subcommand)]
 enum Command {
-    /// Initialized deployment configuration in current directory
+    /// Initialized deployment configuration in current directory.
+    ///
+    /// Settings that are not passed are prompted for. Without a terminal their defaults are used.
     Init {
         /// Name of resource to be deployed
         name: String,
+
+        #[command(flatten)]
+        options: InitArgs,
     },
     /// Configures the cluster
     Cluster {
         #[command(subcommand)]
         cluster_command: ClusterCommand,
     },
+    /// Shows the changes shippr made to releases, oldest first
+    Audit {
+        /// Only changes of this release
+        #[arg(long)]
+        release: Option<String>,
+
+        /// Only changes in this namespace
+        #[arg(long, short)]
+        namespace: Option<String>,
+
+        /// Only changes at or after this time: a timestamp (2026-10-01T12:00:00Z), a date (2026-10-01) or a duration before now (7d)
+        #[arg(long, value_parser = shippr::audit::parse_time)]
+        since: Option<Timestamp>,
+
+        /// Only changes at or before this time. Same formats as --since
+        #[arg(long, value_parser = shippr::audit::parse_time)]
+        until: Option<Timestamp>,
+    },
+    /// Shows the settings of shippr.
+    ///
+    /// Settings are merged from the user configuration ($XDG_CONFIG_HOME/shippr/shippr.toml),
+    /// the closest shippr.toml, SHIPPR_* environment variables and command line flags.
+    Config {
+        #[command(subcommand)]
+        config_command: ConfigCommand,
+    },
+    /// Prints the JSON Schema of the deployment file.
+    ///
+    /// Can be used by editors and YAML language servers for completion and validation.
+    Schema,
+    /// Validates deployment files and reports every problem with its location.
+    ///
+    /// Validates the deployment file in the directory.
+    /// If the directory has no deployment file, every deployment file below it is validated.
+    Validate {
+        /// Directory of the deployment file, or a directory containing release directories
+        dir: PathBuf,
+
+        /// How many directory levels below the directory are searched for deployment files
+        #[arg(long, default_value_t = 1)]
+        depth: usize,
+    },
     /// Verifies that the chart can be deployed
     Check {
         /// Profile to deploy (e.g. dev/prod etc.)
@@ -43,6 +111,31 @@ enum Command {
 
         #[command(flatten)]
         args: ActionArgs,
+
+        /// Checks the rendered manifests against the policies of the settings:
+        /// no latest image tags, resource requests and limits, no privileged containers,
+        /// required labels and the namespace of the deployment file
+        #[arg(long, action = ArgAction::SetTrue)]
+        policies: bool,
+
+        /// Checks every deployment below the directory with every profile that has a values file.
+        /// The releases are checked at the same time and summarized in a table
+        #[arg(long, short = 'r', action = ArgAction::SetTrue)]
+        recursive: bool,
+
+        /// How many directory levels below the directory are searched by --recursive
+        #[arg(long, default_value_t = 1, requires = "recursive")]
+        depth: usize,
+
+        /// Renders the chart with helm template instead of a dry-run against the cluster.
+        /// Needs neither cluster access nor kubectl
+        #[arg(long, action = ArgAction::SetTrue)]
+        offline: bool,
+
+        /// Kubernetes version the chart is rendered against by --offline, e.g. 1.31.0.
+        /// [Default: kube_version of the settings, or the version helm was built with]
+        #[arg(long, requires = "offline")]
+        kube_version: Option<String>,
     },
     /// Deploys helm chart by its deployment file
     Deploy {
@@ -52,16 +145,76 @@ enum Command {
 
         #[command(flatten)]
         args: ActionArgs,
+
+        #[command(flatten)]
+        options: DeployArgs,
+
+        #[command(flatten)]
+        lock: LockArgs,
     },
+    /// Renders the manifests of the release with helm template.
+    ///
+    /// Uses the same values, chart version and location as a deploy
+    Template {
+        /// Profile to render (e.g. dev/prod etc.)
+        #[arg(long, short = 'p')]
+        profile: Option<String>,
+
+        #[command(flatten)]
+        args: ActionArgs,
+
+        /// Writes every object into <kind>-<name>.yaml in this directory instead of printing them.
+        /// Releases of a directory with multiple deployment files get their own subdirectory
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Lints the values files against the values and schema of the chart.
+    ///
+    /// Reports unknown keys, type mismatches and profile keys that repeat the default value
+    LintValues {
+        /// Profile to lint. [Default: every profile with a values file]
+        #[arg(long, short = 'p')]
+        profile: Option<String>,
+
+        #[command(flatten)]
+        args: ActionArgs,
+    },
+    /// Runs the tests of the chart of the release and prints the logs of the test pods.
+    ///
+    /// helm prints the logs only once all tests finished, so a hanging test shows nothing until the timeout
+    Test {
+        #[command(flatten)]
+        args: ActionArgs,
+    },
+    /// Shows the status, revision and chart version of the releases in the cluster
+    Status {
+        #[command(flatten)]
+        args: ActionArgs,
+    },
+    /// Recovers releases stuck in a pending state after an interrupted deploy.
+    ///
+    /// Rolls back to the last successful revision. Without one, the pending revision is removed
+    Unlock {
+        #[command(flatten)]
+        args: ActionArgs,
+    },
+    /// Undeploys the release defined by its deployment file
     Undeploy {
         #[command(flatten)]
         args: ActionArgs,
+
+        #[command(flatten)]
+        dry_run: DryRunArgs,
+
+        #[command(flatten)]
+        lock: LockArgs,
     },
     /// Cleans up any releases that are deployed but not defined.
     ///
     /// For this it uses the given namespace and the context directory.
     /// It reads the folder names of the context directories
     /// and compares them with the release-names in the given namespace.
+    /// Aborts if any deployment file is invalid, unless --no-strict is passed.
     Cleanup {
         #[command(flatten)]
         args: ActionArgs,
@@ -73,6 +226,47 @@ enum Command {
         /// Namespace to cleanup.
         #[arg(long, short = 'n')]
         namespace: Option<String>,
+
+        /// How many directory levels below the context directory are searched for releases.
+        /// Directories containing a deployment file are not searched any further
+        #[arg(long, default_value_t = 1)]
+        depth: usize,
+
+        /// Skips invalid deployment files instead of aborting the cleanup
+        #[arg(long = "no-strict", action = ArgAction::SetFalse)]
+        strict: bool,
+
+        #[command(flatten)]
+        dry_run: DryRunArgs,
+
+        #[command(flatten)]
+        lock: LockArgs,
+    },
+    /// Imports releases deployed with helm into release directories.
+    ///
+    /// Generates a deployment file and a values-default.yaml with the values supplied by the user.
+    /// The chart location is looked up in the configured helm repositories.
+    /// Existing directories are never overwritten.
+    Import {
+        /// Directory in which the release directories are created
+        dir: PathBuf,
+
+        /// Import all namespaces. The release directories are grouped by namespace
+        #[arg(long, short = 'A', action = ArgAction::SetTrue)]
+        all_namespaces: bool,
+
+        /// Namespace to import.
+        #[arg(long, short = 'n')]
+        namespace: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Prints the effective settings and where each of them came from
+    Show {
+        /// Directory from which shippr.toml is searched. [Default: working directory]
+        dir: Option<PathBuf>,
     },
 }
 
@@ -93,50 +287,410 @@ struct ActionArgs {
     /// Context directory. Should be the location of the deployment.yaml for deployments
     /// and the namespace directory for cleanups
     dir: PathBuf,
+
+    /// Name of the deployment file, without extension.
+    /// [Default: deployment_file of the closest shippr.toml, or deployment]
+    #[arg(long, short = 'f')]
+    file: Option<String>,
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+#[derive(Debug, Args)]
+struct InitArgs {
+    /// Name of the chart. [Default: name of the resource]
+    #[arg(long)]
+    chart: Option<String>,
+
+    /// URL of the chart repository
+    #[arg(long, group = "location")]
+    repo: Option<String>,
+
+    /// OCI registry containing the chart, e.g. oci://registry.example.com/charts
+    #[arg(long, group = "location")]
+    oci: Option<String>,
+
+    /// Path of the chart directory
+    #[arg(long, group = "location")]
+    local: Option<String>,
+
+    /// Version of the chart. [Default: latest version]
+    #[arg(long)]
+    version: Option<String>,
+
+    /// Namespace to deploy to. [Default: default]
+    #[arg(long, short = 'n')]
+    namespace: Option<String>,
+
+    /// Profiles to create values files for, e.g. dev,prod
+    #[arg(long, value_delimiter = ',')]
+    profiles: Option<Vec<String>>,
+
+    /// Format of the deployment file
+    #[arg(long, value_enum, default_value_t = DeploymentFormat::Yaml)]
+    format: DeploymentFormat,
+
+    /// Fills values-default.yaml with the default values of the chart
+    #[arg(long, action = ArgAction::SetTrue)]
+    from_chart: bool,
+
+    /// Comments out the default values of the chart, so only changed values have to be uncommented
+    #[arg(long, action = ArgAction::SetTrue, requires = "from_chart")]
+    commented: bool,
+}
+
+#[derive(Debug, Args)]
+struct DeployArgs {
+    /// Verifies the workloads of the release after the deploy:
+    /// finished rollouts, no restarting containers and no failed jobs
+    #[arg(long, action = ArgAction::SetTrue)]
+    health_check: bool,
+
+    /// How many seconds the pods are watched for restarts by --health-check
+    #[arg(long, default_value_t = 30, requires = "health_check")]
+    health_window: u64,
+
+    /// Runs the tests of the chart after the deploy. Failing tests fail the deploy.
+    /// helm prints the logs of the test pods only once all tests finished,
+    /// so a hanging test shows nothing until the timeout
+    #[arg(long, action = ArgAction::SetTrue)]
+    run_tests: bool,
+
+    /// Restores the last successful revision if the deploy, its health check or its tests fail.
+    /// A release that was never deployed successfully is uninstalled
+    #[arg(long, action = ArgAction::SetTrue)]
+    auto_rollback: bool,
+
+    /// Recovers a release stuck in pending-install, pending-upgrade or pending-rollback
+    /// before deploying it, like the unlock command
+    #[arg(long, action = ArgAction::SetTrue)]
+    unlock_pending: bool,
+}
+
+#[derive(Debug, Args)]
+struct LockArgs {
+    /// How many seconds to wait for the lock of a release that is changed by someone else.
+    /// [Default: fail immediately]
+    #[arg(long, value_name = "SECONDS")]
+    wait_for_lock: Option<u64>,
+}
+
+impl LockArgs {
+    fn wait(&self) -> Option<Duration> {
+        self.wait_for_lock.map(Duration::from_secs)
+    }
+}
+
+#[derive(Debug, Args)]
+struct DryRunArgs {
+    /// Only prints the helm commands that would be run.
+    /// Exits with code 3 if any release would be removed
+    #[arg(long, action = ArgAction::SetTrue)]
+    dry_run: bool,
+}
+
+fn main() -> Result<ExitCode, Box<dyn Error>> {
     let app = App::parse();
 
     setup_logger(&app);
 
-    command_available("kubectl")?;
-    command_available("helm")?;
+    let mut overrides = Vec::new();
+    if let Some(format) = app.output {
+        overrides.push(("output", format.to_string()));
+    }
+    if let Command::Check {
+        kube_version: Some(version),
+        ..
+    } = &app.command
+    {
+        overrides.push(("kube_version", version.clone()));
+    }
+    let settings = Settings::load(app.command.context_dir(), &overrides)?;
+    output::init(settings.output);
+    settings::init(settings);
+
+    let started = Instant::now();
+    let command_name = app.command.name();
+    // The schema is the output itself, so it is not wrapped in a report
+    let reported = !matches!(app.command, Command::Schema);
+    let result = run(app.command);
+    if reported {
+        output::finish(command_name, started, result.as_ref().err());
+    }
+
+    result
+}
 
-    match app.command {
-        Command::Init { name } => shippr::actions::initialize_configuration(name)?,
+fn run(command: Command) -> Result<ExitCode, Box<dyn Error>> {
+    shippr::tools::require(&command.tools())?;
+    let exit_code = execute(command)?;
+    // Failures that were only reported, e.g. by helm during a check, fail the process as well
+    output::ensure_success()?;
 
-        Command::Check { profile, args } => shippr::actions::check(profile, args.dir)?,
+    Ok(exit_code)
+}
+
+fn execute(command: Command) -> Result<ExitCode, Box<dyn Error>> {
+    match command {
+        Command::Init { name, options } => {
+            shippr::actions::initialize_configuration(name, options.into())?
+        }
+
+        Command::Schema => shippr::actions::print_schema()?,
+
+        Command::Validate { dir, depth } => shippr::actions::validate(dir, depth)?,
+
+        Command::Check {
+            profile,
+            args,
+            policies,
+            recursive,
+            depth,
+            offline,
+            ..
+        } => {
+            let options = CheckOptions {
+                policies,
+                recursive,
+                depth,
+                offline,
+            };
+            shippr::actions::check(profile, args.dir, args.file, options)?
+        }
 
         Command::Cleanup {
             namespace,
             all_namespaces,
+            depth,
+            strict,
             args,
+            dry_run,
+            lock,
         } => {
-            if !all_namespaces {
+            let discovery = shippr::actions::Discovery { depth, strict };
+            let undeployed = if !all_namespaces {
                 if let Some(ns) = namespace {
-                    shippr::actions::cleanup_namespace(ns, args.dir, args.no_verify)?
+                    shippr::actions::cleanup_namespace(
+                        ns,
+                        args.dir,
+                        args.file,
+                        discovery,
+                        args.no_verify,
+                        dry_run.dry_run,
+                        lock.wait(),
+                    )?
                 } else {
                     return Err(shippr::Error::NoNamespacePassed.into());
                 }
             } else {
-                shippr::actions::cleanup_all_namespaces(args.dir, args.no_verify)?
+                shippr::actions::cleanup_all_namespaces(
+                    args.dir,
+                    args.file,
+                    discovery,
+                    args.no_verify,
+                    dry_run.dry_run,
+                    lock.wait(),
+                )?
+            };
+
+            return Ok(dry_run.exit_code(undeployed));
+        }
+
+        Command::Import {
+            dir,
+            all_namespaces,
+            namespace,
+        } => {
+            if !all_namespaces && namespace.is_none() {
+                return Err(shippr::Error::NoNamespacePassed.into());
             }
+            let namespace = if all_namespaces { None } else { namespace };
+            shippr::actions::import(namespace, dir)?
         }
 
+        Command::Audit {
+            release,
+            namespace,
+            since,
+            until,
+        } => shippr::actions::audit(AuditQuery {
+            release,
+            namespace,
+            since,
+            until,
+        })?,
+
+        Command::Config { config_command } => match config_command {
+            ConfigCommand::Show { .. } => shippr::actions::show_config()?,
+        },
+
         Command::Cluster { cluster_command } => match cluster_command {
             ClusterCommand::List => shippr::actions::list_clusters()?,
             ClusterCommand::Set { name } => shippr::actions::set_cluster(&name)?,
         },
 
-        Command::Deploy { profile, args } => {
-            shippr::actions::deploy(profile, args.dir, args.no_verify)?
+        Command::Deploy {
+            profile,
+            args,
+            options,
+            lock,
+        } => {
+            let options = DeployOptions {
+                no_verify: args.no_verify,
+                health_check: options
+                    .health_check
+                    .then(|| Duration::from_secs(options.health_window)),
+                run_tests: options.run_tests,
+                auto_rollback: options.auto_rollback,
+                wait_for_lock: lock.wait(),
+                unlock_pending: options.unlock_pending,
+            };
+            shippr::actions::deploy(profile, args.dir, args.file, options)?
+        }
+
+        Command::Template { profile, args, out } => {
+            shippr::actions::template(profile, args.dir, args.file, out)?
+        }
+
+        Command::LintValues { profile, args } => {
+            shippr::actions::lint_values(profile, args.dir, args.file)?
+        }
+
+        Command::Test { args } => shippr::actions::test(args.dir, args.file)?,
+
+        Command::Status { args } => shippr::actions::status(args.dir, args.file)?,
+
+        Command::Unlock { args } => shippr::actions::unlock(args.dir, args.file, args.no_verify)?,
+
+        Command::Undeploy {
+            args,
+            dry_run,
+            lock,
+        } => {
+            let undeployed = shippr::actions::undeploy(
+                args.dir,
+                args.file,
+                args.no_verify,
+                dry_run.dry_run,
+                lock.wait(),
+            )?;
+
+            return Ok(dry_run.exit_code(undeployed));
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+impl Command {
+    fn name(&self) -> &'static str {
+        match self {
+            Command::Init { .. } => "init",
+            Command::Cluster {
+                cluster_command: ClusterCommand::List,
+            } => "cluster list",
+            Command::Cluster {
+                cluster_command: ClusterCommand::Set { .. },
+            } => "cluster set",
+            Command::Config {
+                config_command: ConfigCommand::Show { .. },
+            } => "config show",
+            Command::Schema => "schema",
+            Command::Validate { .. } => "validate",
+            Command::Check { .. } => "check",
+            Command::Deploy { .. } => "deploy",
+            Command::Template { .. } => "template",
+            Command::LintValues { .. } => "lint-values",
+            Command::Test { .. } => "test",
+            Command::Status { .. } => "status",
+            Command::Unlock { .. } => "unlock",
+            Command::Undeploy { .. } => "undeploy",
+            Command::Cleanup { .. } => "cleanup",
+            Command::Import { .. } => "import",
+            Command::Audit { .. } => "audit",
         }
+    }
 
-        Command::Undeploy { args } => shippr::actions::undeploy(args.dir, args.no_verify)?,
+    /// Tools the command runs. Tools only needed by some options are only required with them
+    fn tools(&self) -> Vec<Tool> {
+        let locks_in_cluster = settings::get().lock_namespace.is_some();
+        match self {
+            Command::Init { options, .. } if options.from_chart => vec![Tool::Helm],
+            Command::Init { .. }
+            | Command::Audit { .. }
+            | Command::Config { .. }
+            | Command::Schema
+            | Command::Validate { .. } => vec![],
+            Command::Cluster { .. } => vec![Tool::Kubectl],
+            Command::Check { .. }
+            | Command::Template { .. }
+            | Command::LintValues { .. }
+            | Command::Test { .. }
+            | Command::Status { .. }
+            | Command::Import { .. } => vec![Tool::Helm],
+            Command::Deploy { options, .. }
+                if options.health_check || options.unlock_pending || locks_in_cluster =>
+            {
+                vec![Tool::Helm, Tool::Kubectl]
+            }
+            Command::Undeploy { .. } | Command::Cleanup { .. } if locks_in_cluster => {
+                vec![Tool::Helm, Tool::Kubectl]
+            }
+            Command::Deploy { .. } | Command::Undeploy { .. } | Command::Cleanup { .. } => {
+                vec![Tool::Helm]
+            }
+            Command::Unlock { .. } => vec![Tool::Helm, Tool::Kubectl],
+        }
     }
 
-    Ok(())
+    /// Directory from which the settings are searched
+    fn context_dir(&self) -> &Path {
+        match self {
+            Command::Validate { dir, .. } | Command::Import { dir, .. } => dir,
+            Command::Config {
+                config_command: ConfigCommand::Show { dir: Some(dir) },
+            } => dir,
+            Command::Check { args, .. }
+            | Command::Deploy { args, .. }
+            | Command::Template { args, .. }
+            | Command::LintValues { args, .. }
+            | Command::Test { args, .. }
+            | Command::Status { args, .. }
+            | Command::Unlock { args, .. }
+            | Command::Undeploy { args, .. }
+            | Command::Cleanup { args, .. } => &args.dir,
+            _ => Path::new("."),
+        }
+    }
+}
+
+impl From<InitArgs> for InitOptions {
+    fn from(value: InitArgs) -> Self {
+        let location = value
+            .repo
+            .map(ChartLocation::Repo)
+            .or(value.oci.map(ChartLocation::Oci))
+            .or(value.local.map(ChartLocation::Local));
+
+        InitOptions {
+            chart: value.chart,
+            location,
+            version: value.version,
+            namespace: value.namespace,
+            profiles: value.profiles,
+            format: value.format,
+            from_chart: value.from_chart,
+            commented: value.commented,
+        }
+    }
+}
+
+impl DryRunArgs {
+    fn exit_code(&self, undeployed: usize) -> ExitCode {
+        if self.dry_run && undeployed > 0 {
+            ExitCode::from(DRY_RUN_CHANGES_EXIT_CODE)
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
 }
 
 fn setup_logger(app: &App) {
@@ -154,17 +708,3 @@ fn setup_logger(app: &App) {
         .without_time()
         .init();
 }
-
-fn command_available(command: &str) -> Result<(), Box<dyn Error>> {
-    let output = process::Command::new(command)
-        .arg("version")
-        .output()
-        .map_err(|_| shippr::Error::MissingTool(command.to_string()))?;
-
-    if output.status.success() {
-        Ok(())
-    } else {
-        error!("{output:?}");
-        Err(shippr::Error::FaultyTool(command.to_string()).into())
-    }
-}
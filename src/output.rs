@@ -0,0 +1,516 @@
+use std::{
+    io::{Write, stderr, stdout},
+    sync::{Mutex, OnceLock},
+    time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Error, Result,
+    actions::HealthCheck,
+    audit::AuditRecord,
+    command::{Command, Execution},
+    lint::Finding,
+    policy::{Severity, Violation},
+};
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+static REPORT: Mutex<Report> = Mutex::new(Report::new());
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human readable messages on stdout
+    #[default]
+    Text,
+    /// A single JSON document on stdout. Human readable messages are written to stderr
+    Json,
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Sets the output format for the whole process. Can only be set once
+pub fn init(format: OutputFormat) {
+    let _ = FORMAT.set(format);
+}
+
+pub(crate) fn is_json() -> bool {
+    FORMAT.get().is_some_and(|f| *f == OutputFormat::Json)
+}
+
+/// Prints a message meant for humans.
+/// Goes to stdout for text output and to stderr for JSON output, to keep stdout parseable
+macro_rules! message {
+    ($($arg:tt)*) => {
+        $crate::output::print_message(&format!($($arg)*))
+    };
+}
+pub(crate) use message;
+
+pub(crate) fn print_message(message: &str) {
+    if is_json() {
+        eprintln!("{message}");
+    } else {
+        println!("{message}");
+    }
+}
+
+/// Writes output of an external tool. Follows the same rules as [message]
+pub(crate) fn passthrough(output: &[u8]) -> std::io::Result<()> {
+    if is_json() {
+        stderr().write_all(output)
+    } else {
+        stdout().write_all(output)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum ReleaseAction {
+    Check,
+    Deploy,
+    Undeploy,
+    Test,
+    Rollback,
+    Hook,
+    /// A release stuck in a pending state was recovered
+    Unlock,
+    /// Would be undeployed, but the command ran as a dry-run
+    PlannedUndeploy,
+}
+
+impl std::fmt::Display for ReleaseAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ReleaseAction::Check => "check",
+            ReleaseAction::Deploy => "deploy",
+            ReleaseAction::Undeploy => "undeploy",
+            ReleaseAction::Test => "test",
+            ReleaseAction::Rollback => "rollback",
+            ReleaseAction::Hook => "hook",
+            ReleaseAction::Unlock => "unlock",
+            ReleaseAction::PlannedUndeploy => "planned-undeploy",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    command: String,
+    success: bool,
+    duration_ms: u128,
+    releases: Vec<ReleaseReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    clusters: Vec<ClusterReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    config: Vec<ConfigReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    health: Vec<HealthReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    policies: Vec<PolicyReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    values: Vec<ValuesReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    checks: Vec<CheckReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    audit: Vec<AuditRecord>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    status: Vec<StatusReport>,
+    errors: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct ReleaseReport {
+    name: String,
+    namespace: String,
+    action: ReleaseAction,
+    command: String,
+    exit_code: Option<i32>,
+    duration_ms: Option<u128>,
+    /// What the command wrote to stderr, if it failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct ClusterReport {
+    pub(crate) name: String,
+    pub(crate) current: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct HealthReport {
+    name: String,
+    namespace: String,
+    healthy: bool,
+    checks: Vec<HealthCheck>,
+}
+
+#[derive(Debug, Serialize)]
+struct PolicyReport {
+    name: String,
+    namespace: String,
+    /// No violation with the severity error
+    passed: bool,
+    violations: Vec<Violation>,
+}
+
+#[derive(Debug, Serialize)]
+struct ValuesReport {
+    name: String,
+    namespace: String,
+    /// No finding with the severity error
+    passed: bool,
+    findings: Vec<Finding>,
+}
+
+/// Result of checking a release with a profile
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct CheckReport {
+    pub(crate) release: String,
+    pub(crate) profile: Option<String>,
+    pub(crate) passed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) error: Option<String>,
+}
+
+/// State of a release in the cluster, as reported by helm status
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct StatusReport {
+    pub(crate) name: String,
+    pub(crate) namespace: String,
+    /// e.g. deployed, failed or pending-upgrade. not-deployed if the release does not exist
+    pub(crate) status: String,
+    pub(crate) revision: Option<u64>,
+    pub(crate) chart_version: Option<String>,
+    pub(crate) app_version: Option<String>,
+    pub(crate) last_deployed: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct ConfigReport {
+    pub(crate) key: String,
+    pub(crate) value: serde_json::Value,
+    pub(crate) source: String,
+}
+
+impl Report {
+    /// Everything recorded that failed
+    fn failures(&self) -> Vec<String> {
+        let releases = self.releases.iter().filter_map(|release| {
+            let code = release.exit_code.filter(|code| *code != 0)?;
+            Some(format!(
+                "{} of {} exited with code {code}",
+                release.action, release.name
+            ))
+        });
+        let health = self
+            .health
+            .iter()
+            .filter(|health| !health.healthy)
+            .map(|health| format!("{} is unhealthy", health.name));
+        let policies = self
+            .policies
+            .iter()
+            .filter(|policies| !policies.passed)
+            .map(|policies| format!("{} violates policies", policies.name));
+        let values = self
+            .values
+            .iter()
+            .filter(|values| !values.passed)
+            .map(|values| format!("the values files of {} do not match the chart", values.name));
+        let checks = self
+            .checks
+            .iter()
+            .filter(|check| !check.passed)
+            .map(|check| format!("the check of {} failed", check.release));
+
+        releases
+            .chain(health)
+            .chain(policies)
+            .chain(values)
+            .chain(checks)
+            .collect()
+    }
+
+    const fn new() -> Self {
+        Self {
+            command: String::new(),
+            success: true,
+            duration_ms: 0,
+            releases: Vec::new(),
+            clusters: Vec::new(),
+            config: Vec::new(),
+            health: Vec::new(),
+            policies: Vec::new(),
+            values: Vec::new(),
+            checks: Vec::new(),
+            audit: Vec::new(),
+            status: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+}
+
+/// Records a release for which a command was run
+pub(crate) fn record_executed(
+    name: &str,
+    namespace: &str,
+    action: ReleaseAction,
+    execution: Execution,
+) {
+    record(ReleaseReport {
+        name: name.to_string(),
+        namespace: namespace.to_string(),
+        action,
+        command: execution.command,
+        exit_code: execution.exit_code,
+        duration_ms: Some(execution.duration.as_millis()),
+        error: Some(execution.stderr.trim().to_string())
+            .filter(|stderr| execution.exit_code != Some(0) && !stderr.is_empty()),
+    });
+}
+
+/// Records a release for which a command would have been run
+pub(crate) fn record_planned(
+    name: &str,
+    namespace: &str,
+    action: ReleaseAction,
+    command: &Command,
+) {
+    record(ReleaseReport {
+        name: name.to_string(),
+        namespace: namespace.to_string(),
+        action,
+        command: command.to_string(),
+        exit_code: None,
+        duration_ms: None,
+        error: None,
+    });
+}
+
+fn record(release: ReleaseReport) {
+    if let Ok(mut report) = REPORT.lock() {
+        report.releases.push(release);
+    }
+}
+
+pub(crate) fn record_clusters(clusters: Vec<ClusterReport>) {
+    if let Ok(mut report) = REPORT.lock() {
+        report.clusters = clusters;
+    }
+}
+
+/// Records the result of the health checks of a deployed release
+pub(crate) fn record_health(name: &str, namespace: &str, checks: Vec<HealthCheck>) {
+    if let Ok(mut report) = REPORT.lock() {
+        report.health.push(HealthReport {
+            name: name.to_string(),
+            namespace: namespace.to_string(),
+            healthy: checks.iter().all(|check| check.healthy),
+            checks,
+        });
+    }
+}
+
+/// Records the policy violations of a rendered release
+pub(crate) fn record_policies(name: &str, namespace: &str, violations: Vec<Violation>) {
+    if let Ok(mut report) = REPORT.lock() {
+        report.policies.push(PolicyReport {
+            name: name.to_string(),
+            namespace: namespace.to_string(),
+            passed: violations
+                .iter()
+                .all(|violation| violation.severity != Severity::Error),
+            violations,
+        });
+    }
+}
+
+/// Records the findings of linting the values files of a release
+pub(crate) fn record_values(name: &str, namespace: &str, findings: Vec<Finding>) {
+    if let Ok(mut report) = REPORT.lock() {
+        report.values.push(ValuesReport {
+            name: name.to_string(),
+            namespace: namespace.to_string(),
+            passed: findings
+                .iter()
+                .all(|finding| finding.severity != Severity::Error),
+            findings,
+        });
+    }
+}
+
+pub(crate) fn record_checks(checks: Vec<CheckReport>) {
+    if let Ok(mut report) = REPORT.lock() {
+        report.checks = checks;
+    }
+}
+
+pub(crate) fn record_audit(audit: Vec<AuditRecord>) {
+    if let Ok(mut report) = REPORT.lock() {
+        report.audit = audit;
+    }
+}
+
+pub(crate) fn record_statuses(statuses: Vec<StatusReport>) {
+    if let Ok(mut report) = REPORT.lock() {
+        report.status = statuses;
+    }
+}
+
+pub(crate) fn record_config(config: Vec<ConfigReport>) {
+    if let Ok(mut report) = REPORT.lock() {
+        report.config = config;
+    }
+}
+
+/// Fails if anything recorded failed, even if it was only reported
+pub fn ensure_success() -> Result<()> {
+    let failures = match REPORT.lock() {
+        Ok(report) => report.failures(),
+        Err(_) => Vec::new(),
+    };
+    if !failures.is_empty() {
+        return Err(Error::Unsuccessful(failures));
+    }
+
+    Ok(())
+}
+
+/// Prints the JSON document of everything recorded. Does nothing for text output
+pub fn finish<E: std::fmt::Display>(command: &str, started: Instant, error: Option<&E>) {
+    if !is_json() {
+        return;
+    }
+
+    let Ok(mut report) = REPORT.lock() else {
+        return;
+    };
+    report.command = command.to_string();
+    report.duration_ms = started.elapsed().as_millis();
+    if let Some(err) = error {
+        report.errors.push(err.to_string());
+    }
+    report.success = report.errors.is_empty() && report.failures().is_empty();
+
+    match serde_json::to_string_pretty(&*report) {
+        Ok(json) => println!("{json}"),
+        Err(err) => eprintln!("Failed to serialize output: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CheckReport, ClusterReport, ReleaseAction, ReleaseReport, Report};
+
+    type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+    #[test]
+    fn serialize_report() -> TestResult {
+        // given
+        let mut report = Report::new();
+        report.command = String::from("undeploy");
+        report.releases.push(ReleaseReport {
+            name: String::from("TestRelease"),
+            namespace: String::from("TestNamespace"),
+            action: ReleaseAction::PlannedUndeploy,
+            command: String::from("helm uninstall TestRelease --namespace TestNamespace"),
+            exit_code: None,
+            duration_ms: None,
+            error: None,
+        });
+
+        // when
+        let result = serde_json::to_value(&report)?;
+
+        // then
+        assert_eq!(
+            result,
+            serde_json::json!({
+                "command": "undeploy",
+                "success": true,
+                "duration_ms": 0,
+                "releases": [{
+                    "name": "TestRelease",
+                    "namespace": "TestNamespace",
+                    "action": "planned-undeploy",
+                    "command": "helm uninstall TestRelease --namespace TestNamespace",
+                    "exit_code": null,
+                    "duration_ms": null,
+                }],
+                "errors": [],
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn serialize_clusters_only_when_present() -> TestResult {
+        // given
+        let mut report = Report::new();
+        report.clusters.push(ClusterReport {
+            name: String::from("kind"),
+            current: true,
+        });
+
+        // when
+        let result = serde_json::to_value(&report)?;
+
+        // then
+        assert_eq!(
+            result["clusters"],
+            serde_json::json!([{ "name": "kind", "current": true }])
+        );
+        assert!(
+            serde_json::to_value(Report::new())?
+                .get("clusters")
+                .is_none()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reported_failures_make_report_unsuccessful() {
+        // given
+        let mut report = Report::new();
+        report.releases.push(ReleaseReport {
+            name: String::from("TestRelease"),
+            namespace: String::from("TestNamespace"),
+            action: ReleaseAction::Check,
+            command: String::from("helm upgrade --install --dry-run"),
+            exit_code: Some(1),
+            duration_ms: Some(100),
+            error: Some(String::from("Error: chart not found")),
+        });
+        report.checks.push(CheckReport {
+            release: String::from("OtherRelease"),
+            profile: None,
+            passed: false,
+            error: None,
+        });
+
+        // when
+        let result = report.failures();
+
+        // then
+        assert_eq!(
+            result,
+            [
+                "check of TestRelease exited with code 1",
+                "the check of OtherRelease failed",
+            ]
+        );
+    }
+}
@@ -0,0 +1,348 @@
+use std::fmt;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::{Error, Result};
+
+/// Built-in rules rendered manifests are checked against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Policy {
+    /// Images must have a tag other than latest, or a digest
+    LatestTag,
+    /// Containers must have resource requests and limits
+    Resources,
+    /// Containers must not be privileged
+    Privileged,
+    /// Objects must have the labels of the settings
+    RequiredLabels,
+    /// Objects must be in the namespace of the deployment file
+    Namespace,
+}
+
+impl Policy {
+    pub(crate) const ALL: [Policy; 5] = [
+        Policy::LatestTag,
+        Policy::Resources,
+        Policy::Privileged,
+        Policy::RequiredLabels,
+        Policy::Namespace,
+    ];
+}
+
+impl fmt::Display for Policy {
+    /// Named like the key in the settings
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Policy::LatestTag => "latest_tag",
+            Policy::Resources => "resources",
+            Policy::Privileged => "privileged",
+            Policy::RequiredLabels => "required_labels",
+            Policy::Namespace => "namespace",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Fails the check
+    Error,
+    /// Is reported, but does not fail the check
+    Warning,
+    /// Is not checked
+    Off,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Off => "off",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Severity of every policy and the labels required by required_labels
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PolicySettings {
+    pub latest_tag: Severity,
+    pub resources: Severity,
+    pub privileged: Severity,
+    pub required_labels: Severity,
+    pub namespace: Severity,
+    /// Labels every object must have
+    #[serde(deserialize_with = "crate::settings::comma_separated")]
+    pub labels: Vec<String>,
+}
+
+impl Default for PolicySettings {
+    fn default() -> Self {
+        Self {
+            latest_tag: Severity::Error,
+            resources: Severity::Warning,
+            privileged: Severity::Error,
+            required_labels: Severity::Warning,
+            namespace: Severity::Error,
+            labels: vec![
+                String::from("app.kubernetes.io/name"),
+                String::from("app.kubernetes.io/instance"),
+            ],
+        }
+    }
+}
+
+impl PolicySettings {
+    fn severity(&self, policy: Policy) -> Severity {
+        match policy {
+            Policy::LatestTag => self.latest_tag,
+            Policy::Resources => self.resources,
+            Policy::Privileged => self.privileged,
+            Policy::RequiredLabels => self.required_labels,
+            Policy::Namespace => self.namespace,
+        }
+    }
+}
+
+/// An object of a manifest breaking a policy
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct Violation {
+    pub(crate) policy: Policy,
+    pub(crate) severity: Severity,
+    /// Kind and name of the object, e.g. Deployment/nginx
+    pub(crate) resource: String,
+    pub(crate) details: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}: {} ({})",
+            self.severity, self.resource, self.details, self.policy
+        )
+    }
+}
+
+/// Checks every object of the rendered manifest against the policies.
+/// Policies that are off or suppressed for the release are skipped
+pub(crate) fn evaluate(
+    manifest: &str,
+    namespace: &str,
+    settings: &PolicySettings,
+    suppressed: &[Policy],
+) -> Result<Vec<Violation>> {
+    let objects =
+        YamlLoader::load_from_str(manifest).map_err(|err| Error::YamlError(err.to_string()))?;
+
+    let mut violations = Vec::new();
+    for object in &objects {
+        let (Some(kind), Some(name)) =
+            (object["kind"].as_str(), object["metadata"]["name"].as_str())
+        else {
+            continue;
+        };
+        let resource = format!("{kind}/{name}");
+
+        for policy in Policy::ALL {
+            let severity = settings.severity(policy);
+            if severity == Severity::Off || suppressed.contains(&policy) {
+                continue;
+            }
+
+            violations.extend(check(policy, object, namespace, settings).into_iter().map(
+                |details| Violation {
+                    policy,
+                    severity,
+                    resource: resource.clone(),
+                    details,
+                },
+            ));
+        }
+    }
+
+    Ok(violations)
+}
+
+/// Details of every violation of the policy by the object
+fn check(policy: Policy, object: &Yaml, namespace: &str, settings: &PolicySettings) -> Vec<String> {
+    match policy {
+        Policy::LatestTag => containers(object)
+            .filter_map(|(name, container)| {
+                let image = container["image"].as_str()?;
+                uses_latest(image)
+                    .then(|| format!("container {name} uses the image {image} without a fixed tag"))
+            })
+            .collect(),
+        Policy::Resources => containers(object)
+            .filter_map(|(name, container)| {
+                let missing: Vec<_> = ["requests", "limits"]
+                    .into_iter()
+                    .filter(|kind| {
+                        container["resources"][*kind]
+                            .as_hash()
+                            .is_none_or(|resources| resources.is_empty())
+                    })
+                    .collect();
+                (!missing.is_empty())
+                    .then(|| format!("container {name} has no resource {}", missing.join(" and ")))
+            })
+            .collect(),
+        Policy::Privileged => containers(object)
+            .filter(|(_, container)| {
+                container["securityContext"]["privileged"].as_bool() == Some(true)
+            })
+            .map(|(name, _)| format!("container {name} is privileged"))
+            .collect(),
+        Policy::RequiredLabels => settings
+            .labels
+            .iter()
+            .filter(|label| object["metadata"]["labels"][label.as_str()].is_badvalue())
+            .map(|label| format!("the label {label} is missing"))
+            .collect(),
+        Policy::Namespace => object["metadata"]["namespace"]
+            .as_str()
+            .filter(|object_namespace| *object_namespace != namespace)
+            .map(|object_namespace| {
+                format!("is in the namespace {object_namespace} instead of {namespace}")
+            })
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// Containers and init containers of a workload, with their names
+fn containers(object: &Yaml) -> impl Iterator<Item = (&str, &Yaml)> {
+    let spec = match object["kind"].as_str() {
+        Some("Pod") => &object["spec"],
+        Some("CronJob") => &object["spec"]["jobTemplate"]["spec"]["template"]["spec"],
+        _ => &object["spec"]["template"]["spec"],
+    };
+
+    ["initContainers", "containers"]
+        .into_iter()
+        .filter_map(|key| spec[key].as_vec())
+        .flatten()
+        .map(|container| (container["name"].as_str().unwrap_or("unnamed"), container))
+}
+
+/// Images pinned by a digest are fixed, whatever their tag
+fn uses_latest(image: &str) -> bool {
+    if image.contains('@') {
+        return false;
+    }
+
+    // A registry port also contains a colon, the tag is only in the last part
+    let name = image.rsplit('/').next().unwrap_or(image);
+    match name.split_once(':') {
+        Some((_, tag)) => tag == "latest",
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::{Policy, PolicySettings, Severity, evaluate, uses_latest};
+
+    type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+    const MANIFEST: &str = "
+apiVersion: v1
+kind: Service
+metadata:
+  name: web
+  namespace: dev
+  labels:
+    app.kubernetes.io/name: web
+    app.kubernetes.io/instance: web
+---
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+  namespace: prod
+  labels:
+    app.kubernetes.io/name: web
+spec:
+  template:
+    spec:
+      initContainers:
+        - name: migrate
+          image: registry.example.com:5000/migrate@sha256:abc
+          resources:
+            requests: { cpu: 100m }
+            limits: { memory: 64Mi }
+      containers:
+        - name: web
+          image: nginx
+          securityContext:
+            privileged: true
+          resources:
+            requests: { cpu: 100m }
+";
+
+    #[test]
+    fn violations_of_every_policy() -> TestResult {
+        // when
+        let result = evaluate(MANIFEST, "dev", &PolicySettings::default(), &[])?;
+
+        // then
+        let result: Vec<_> = result.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            result,
+            [
+                "error: Deployment/web: container web uses the image nginx without a fixed tag (latest_tag)",
+                "warning: Deployment/web: container web has no resource limits (resources)",
+                "error: Deployment/web: container web is privileged (privileged)",
+                "warning: Deployment/web: the label app.kubernetes.io/instance is missing (required_labels)",
+                "error: Deployment/web: is in the namespace prod instead of dev (namespace)",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn suppressed_and_disabled_policies_skipped() -> TestResult {
+        // given
+        let settings = PolicySettings {
+            privileged: Severity::Off,
+            resources: Severity::Error,
+            ..Default::default()
+        };
+
+        // when
+        let result = evaluate(
+            MANIFEST,
+            "dev",
+            &settings,
+            &[Policy::LatestTag, Policy::Namespace, Policy::RequiredLabels],
+        )?;
+
+        // then
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].policy, Policy::Resources);
+        assert_eq!(result[0].severity, Severity::Error);
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("nginx", true)]
+    #[case("nginx:latest", true)]
+    #[case("nginx:1.27", false)]
+    #[case("registry.example.com:5000/nginx", true)]
+    #[case("registry.example.com:5000/nginx:1.27", false)]
+    #[case("nginx:latest@sha256:abc", false)]
+    fn image_tags(#[case] image: &str, #[case] expected: bool) {
+        assert_eq!(uses_latest(image), expected);
+    }
+}
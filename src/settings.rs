@@ -0,0 +1,474 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use config::{Config, Environment, File, FileFormat, Map, ValueKind};
+use serde::{Deserialize, Deserializer, Serialize};
+use tracing::{debug, info};
+
+use crate::{
+    Result, deploy_config::DeploymentFileName, lint::LintSettings, output::OutputFormat,
+    policy::PolicySettings,
+};
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// Name of the project configuration file
+const FILE_NAME: &str = "shippr.toml";
+/// Prefix of environment variables overriding settings, e.g. SHIPPR_TIMEOUT
+const ENV_PREFIX: &str = "SHIPPR";
+
+const SOURCE_DEFAULT: &str = "default";
+const SOURCE_COMMAND_LINE: &str = "command line";
+const SOURCE_ENVIRONMENT: &str = "environment";
+
+/// Settings of shippr. Merged from, in increasing precedence:
+/// defaults, the user configuration, the closest shippr.toml, SHIPPR_* environment variables
+/// and command line flags
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Name of the deployment files, without extension
+    pub deployment_file: String,
+    /// How long helm waits for a release to be ready, e.g. 2m0s
+    pub timeout: String,
+    /// Releases in these namespaces are never undeployed
+    #[serde(deserialize_with = "comma_separated")]
+    pub protected_namespaces: Vec<String>,
+    /// Aliases of chart repositories. Can be used as repo in deployment files
+    pub repositories: BTreeMap<String, String>,
+    /// Output format used if --output is not passed
+    pub output: OutputFormat,
+    /// Prefixes every line of helm and hook output with the name of the release
+    pub prefix_output: bool,
+    /// JSONL file every change to a release is appended to.
+    /// Defaults to shippr/audit.jsonl in the XDG state directory
+    pub audit_log: Option<PathBuf>,
+    /// Namespace in which releases are additionally locked with a Lease while they are changed.
+    /// Without it releases are only locked on this machine
+    pub lock_namespace: Option<String>,
+    /// Kubernetes version charts are rendered against without a cluster, e.g. 1.31.0.
+    /// Defaults to the version helm was built with
+    pub kube_version: Option<String>,
+    /// Path of the helm binary. Searched in the PATH if it is only a name
+    pub helm: String,
+    /// Path of the kubectl binary. Searched in the PATH if it is only a name
+    pub kubectl: String,
+    /// Severity of the policies checked by check --policies
+    pub policies: PolicySettings,
+    /// Severity of the lints of lint-values and check
+    pub lints: LintSettings,
+
+    /// Where each setting came from
+    #[serde(skip)]
+    sources: BTreeMap<String, String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            deployment_file: String::from("deployment"),
+            timeout: String::from("2m0s"),
+            protected_namespaces: Vec::new(),
+            repositories: BTreeMap::new(),
+            output: OutputFormat::Text,
+            prefix_output: false,
+            audit_log: None,
+            lock_namespace: None,
+            kube_version: None,
+            helm: String::from("helm"),
+            kubectl: String::from("kubectl"),
+            policies: PolicySettings::default(),
+            lints: LintSettings::default(),
+            sources: BTreeMap::new(),
+        }
+    }
+}
+
+/// Sets the settings for the whole process. Can only be set once
+pub fn init(settings: Settings) {
+    let _ = SETTINGS.set(settings);
+}
+
+/// Settings of the process. Defaults if they were never set
+pub fn get() -> &'static Settings {
+    SETTINGS.get_or_init(Settings::default)
+}
+
+impl Settings {
+    /// Loads the settings for the context directory
+    pub fn load(dir: &Path, overrides: &[(&str, String)]) -> Result<Self> {
+        Self::load_from(user_file(), project_file(dir), None, overrides)
+    }
+
+    /// Environment variables are read from the process if none are given
+    fn load_from(
+        user_file: Option<PathBuf>,
+        project_file: Option<PathBuf>,
+        environment: Option<Map<String, String>>,
+        overrides: &[(&str, String)],
+    ) -> Result<Self> {
+        let mut builder = Config::builder();
+        for file in [user_file, project_file].into_iter().flatten() {
+            info!("Using the configuration {file:?}");
+            builder = builder.add_source(File::from(file).format(FileFormat::Toml));
+        }
+        builder = builder.add_source(
+            Environment::with_prefix(ENV_PREFIX)
+                .prefix_separator("_")
+                .separator("__")
+                .source(environment),
+        );
+        for (key, value) in overrides {
+            builder = builder.set_override(*key, value.as_str())?;
+        }
+
+        let config = builder.build()?;
+        let mut settings: Settings = config.clone().try_deserialize()?;
+        settings.sources = sources(&config, &settings, overrides);
+        debug!("Loaded the following settings: {settings:?}");
+
+        Ok(settings)
+    }
+
+    /// Every setting, with nested settings flattened into dotted keys
+    pub(crate) fn entries(&self) -> Vec<(String, serde_json::Value, &str)> {
+        let Ok(serde_json::Value::Object(values)) = serde_json::to_value(self) else {
+            return Vec::new();
+        };
+
+        let mut entries = Vec::new();
+        for (key, value) in values {
+            match value {
+                serde_json::Value::Object(table) => {
+                    for (name, value) in table {
+                        entries.push((format!("{key}.{name}"), value));
+                    }
+                }
+                value => entries.push((key, value)),
+            }
+        }
+
+        entries
+            .into_iter()
+            .map(|(key, value)| {
+                let source = self
+                    .sources
+                    .get(&key)
+                    .map(String::as_str)
+                    .unwrap_or(SOURCE_DEFAULT);
+                (key, value, source)
+            })
+            .collect()
+    }
+}
+
+/// A list, or a comma separated string as lists are passed in environment variables.
+/// Environment variables are not parsed by config, as that would turn versions like 1.30 into 1.3
+pub(crate) fn comma_separated<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum List {
+        Items(Vec<String>),
+        Text(String),
+    }
+
+    Ok(match List::deserialize(deserializer)? {
+        List::Items(items) => items,
+        List::Text(text) => text
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(String::from)
+            .collect(),
+    })
+}
+
+/// Finds out where every set value came from
+fn sources(
+    config: &Config,
+    settings: &Settings,
+    overrides: &[(&str, String)],
+) -> BTreeMap<String, String> {
+    let origin = |key: &str| -> Option<String> {
+        if overrides.iter().any(|(k, _)| *k == key) {
+            return Some(String::from(SOURCE_COMMAND_LINE));
+        }
+        raw_value(config, key)?.origin().map(|origin| match origin {
+            "the environment" => String::from(SOURCE_ENVIRONMENT),
+            // Files are given relative to the working directory
+            file => fs::canonicalize(file)
+                .map(|file| file.display().to_string())
+                .unwrap_or_else(|_| file.to_string()),
+        })
+    };
+
+    let mut sources = BTreeMap::new();
+    for key in [
+        "deployment_file",
+        "timeout",
+        "protected_namespaces",
+        "output",
+        "prefix_output",
+        "audit_log",
+        "lock_namespace",
+        "kube_version",
+        "helm",
+        "kubectl",
+        "policies.latest_tag",
+        "policies.resources",
+        "policies.privileged",
+        "policies.required_labels",
+        "policies.namespace",
+        "policies.labels",
+        "lints.unknown_key",
+        "lints.type_mismatch",
+        "lints.repeated_default",
+    ] {
+        if let Some(source) = origin(key) {
+            sources.insert(key.to_string(), source);
+        }
+    }
+    for alias in settings.repositories.keys() {
+        let key = format!("repositories.{alias}");
+        if let Some(source) = origin(&key) {
+            sources.insert(key, source);
+        }
+    }
+
+    sources
+}
+
+/// The merged value, which unlike deserialized values still knows its origin
+fn raw_value<'a>(config: &'a Config, key: &str) -> Option<&'a config::Value> {
+    let mut value = &config.cache;
+    for part in key.split('.') {
+        let ValueKind::Table(table) = &value.kind else {
+            return None;
+        };
+        value = table.get(part)?;
+    }
+
+    Some(value)
+}
+
+/// The user configuration in the XDG config directory
+fn user_file() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_dir.join("shippr").join(FILE_NAME)).filter(|file| file.is_file())
+}
+
+/// Directory for data shippr keeps between runs, in the XDG state directory
+pub(crate) fn state_dir() -> Option<PathBuf> {
+    let state_dir = env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state"))
+        })?;
+
+    Some(state_dir.join("shippr"))
+}
+
+/// The closest shippr.toml in the directory or any of its parents
+fn project_file(dir: &Path) -> Option<PathBuf> {
+    let dir = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+
+    dir.ancestors()
+        .map(|ancestor| ancestor.join(FILE_NAME))
+        .find(|file| file.is_file())
+}
+
+/// Name of the deployment files. A passed name takes precedence over the settings
+pub(crate) fn deployment_file_name(file: Option<String>) -> DeploymentFileName {
+    DeploymentFileName::from(file.unwrap_or_else(|| get().deployment_file.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use config::Map;
+
+    use super::{Settings, project_file};
+    use crate::{output::OutputFormat, policy::Severity};
+
+    type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+    #[test]
+    fn project_file_found_in_parent() -> TestResult {
+        // given
+        let root = tempfile::tempdir()?;
+        fs::write(root.path().join("shippr.toml"), "")?;
+        let dir = root.path().join("dev/nginx");
+        fs::create_dir_all(&dir)?;
+
+        // when
+        let result = project_file(&dir);
+
+        // then
+        assert_eq!(result, Some(root.path().join("shippr.toml")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn merged_by_precedence_with_sources() -> TestResult {
+        // given
+        let root = tempfile::tempdir()?;
+        let user_file = root.path().join("user.toml");
+        fs::write(
+            &user_file,
+            "timeout = \"5m0s\"\ndeployment_file = \"user\"\n[repositories]\nbitnami = \"https://charts.bitnami.com/bitnami\"\n",
+        )?;
+        let project_file = root.path().join("shippr.toml");
+        fs::write(
+            &project_file,
+            "deployment_file = \"release\"\naudit_log = \"/var/log/shippr.jsonl\"\n[policies]\nresources = \"error\"\n[lints]\nunknown_key = \"warning\"\n",
+        )?;
+        let environment = Map::from([
+            (
+                String::from("SHIPPR_PROTECTED_NAMESPACES"),
+                String::from("kube-system,prod"),
+            ),
+            (String::from("SHIPPR_OUTPUT"), String::from("text")),
+            (String::from("SHIPPR_PREFIX_OUTPUT"), String::from("true")),
+            (
+                String::from("SHIPPR_LOCK_NAMESPACE"),
+                String::from("shippr"),
+            ),
+            (String::from("SHIPPR_HELM"), String::from("/opt/helm/helm")),
+        ]);
+
+        // when
+        let result = Settings::load_from(
+            Some(user_file.clone()),
+            Some(project_file.clone()),
+            Some(environment),
+            &[
+                ("output", String::from("json")),
+                ("kube_version", String::from("1.31.0")),
+            ],
+        )?;
+
+        // then
+        assert_eq!(result.deployment_file, "release");
+        assert_eq!(result.timeout, "5m0s");
+        assert_eq!(result.protected_namespaces, ["kube-system", "prod"]);
+        assert_eq!(result.output, OutputFormat::Json);
+        assert!(result.prefix_output);
+        assert_eq!(result.helm, "/opt/helm/helm");
+        assert_eq!(result.policies.resources, Severity::Error);
+        assert_eq!(result.policies.latest_tag, Severity::Error);
+        assert_eq!(result.lints.unknown_key, Severity::Warning);
+
+        let sources: Vec<_> = result
+            .entries()
+            .into_iter()
+            .map(|(key, _, source)| (key, source.to_string()))
+            .collect();
+        assert_eq!(
+            sources,
+            [
+                (
+                    String::from("deployment_file"),
+                    project_file.display().to_string()
+                ),
+                (String::from("timeout"), user_file.display().to_string()),
+                (
+                    String::from("protected_namespaces"),
+                    String::from("environment")
+                ),
+                (
+                    String::from("repositories.bitnami"),
+                    user_file.display().to_string()
+                ),
+                (String::from("output"), String::from("command line")),
+                (String::from("prefix_output"), String::from("environment")),
+                (
+                    String::from("audit_log"),
+                    project_file.display().to_string()
+                ),
+                (String::from("lock_namespace"), String::from("environment")),
+                (String::from("kube_version"), String::from("command line")),
+                (String::from("helm"), String::from("environment")),
+                (String::from("kubectl"), String::from("default")),
+                (String::from("policies.latest_tag"), String::from("default")),
+                (
+                    String::from("policies.resources"),
+                    project_file.display().to_string()
+                ),
+                (String::from("policies.privileged"), String::from("default")),
+                (
+                    String::from("policies.required_labels"),
+                    String::from("default")
+                ),
+                (String::from("policies.namespace"), String::from("default")),
+                (String::from("policies.labels"), String::from("default")),
+                (
+                    String::from("lints.unknown_key"),
+                    project_file.display().to_string()
+                ),
+                (String::from("lints.type_mismatch"), String::from("default")),
+                (
+                    String::from("lints.repeated_default"),
+                    String::from("default")
+                ),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn environment_values_not_parsed_as_numbers() -> TestResult {
+        // given
+        let environment = Map::from([
+            (String::from("SHIPPR_KUBE_VERSION"), String::from("1.30")),
+            (String::from("SHIPPR_LOCK_NAMESPACE"), String::from("1")),
+            (String::from("SHIPPR_PREFIX_OUTPUT"), String::from("true")),
+            (
+                String::from("SHIPPR_POLICIES__LABELS"),
+                String::from("team, tier"),
+            ),
+        ]);
+
+        // when
+        let result = Settings::load_from(None, None, Some(environment), &[])?;
+
+        // then
+        assert_eq!(result.kube_version.as_deref(), Some("1.30"));
+        assert_eq!(result.lock_namespace.as_deref(), Some("1"));
+        assert!(result.prefix_output);
+        assert_eq!(result.policies.labels, ["team", "tier"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn defaults_without_sources() -> TestResult {
+        // when
+        let result = Settings::load_from(None, None, Some(Map::new()), &[])?;
+
+        // then
+        assert_eq!(result.deployment_file, "deployment");
+        assert_eq!(result.timeout, "2m0s");
+        assert!(
+            result
+                .entries()
+                .iter()
+                .all(|(_, _, source)| *source == "default")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use std::{fmt, io::ErrorKind};
+
+use semver::Version;
+use tracing::{debug, error};
+
+use crate::{Error, Result, command::Command, settings};
+
+/// External programs shippr runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Helm,
+    Kubectl,
+}
+
+impl fmt::Display for Tool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Tool::Helm => "helm",
+            Tool::Kubectl => "kubectl",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl Tool {
+    /// Binary of the tool, as configured in the settings
+    pub(crate) fn program(self) -> &'static str {
+        match self {
+            Tool::Helm => &settings::get().helm,
+            Tool::Kubectl => &settings::get().kubectl,
+        }
+    }
+
+    /// Oldest version shippr works with.
+    /// helm supports OCI registries since 3.8, kubectl prints its own version without a cluster since 1.24
+    fn minimum(self) -> Version {
+        match self {
+            Tool::Helm => Version::new(3, 8, 0),
+            Tool::Kubectl => Version::new(1, 24, 0),
+        }
+    }
+
+    /// Prints the version without contacting the cluster
+    fn create_version(self) -> Command {
+        match self {
+            Tool::Helm => {
+                let mut command = Command::helm();
+                command.args(["version", "--short"]);
+                command
+            }
+            Tool::Kubectl => {
+                let mut command = Command::kubectl();
+                command.args(["version", "--client"]);
+                command
+            }
+        }
+    }
+}
+
+/// Verifies that every tool is installed, working and recent enough
+pub fn require(tools: &[Tool]) -> Result<()> {
+    for tool in tools {
+        let output = match tool.create_version().output() {
+            Ok(output) => output,
+            Err(Error::IoError(err)) if err.kind() == ErrorKind::NotFound => {
+                return Err(Error::MissingTool(tool.program().to_string()));
+            }
+            Err(err) => {
+                error!("{err}");
+                return Err(Error::FaultyTool(tool.program().to_string()));
+            }
+        };
+
+        let Some(version) = parse_version(&output) else {
+            error!("Found no version in the output of {tool}: {output}");
+            return Err(Error::FaultyTool(tool.program().to_string()));
+        };
+        debug!("Found {tool} {version}");
+        if version < tool.minimum() {
+            return Err(Error::OutdatedTool(
+                tool.to_string(),
+                version.to_string(),
+                tool.minimum().to_string(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// First version in the output, e.g. v3.15.2+g1a500d5 of helm or
+/// Client Version: v1.31.0 of kubectl
+fn parse_version(output: &str) -> Option<Version> {
+    output
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '-'))
+        .filter_map(|word| Version::parse(word.strip_prefix('v').unwrap_or(word)).ok())
+        .next()
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use semver::Version;
+
+    use super::parse_version;
+
+    #[rstest]
+    #[case("v3.15.2+g1a500d5\n", Some(Version::new(3, 15, 2)))]
+    #[case(
+        "Client Version: v1.31.0\nKustomize Version: v5.4.2\n",
+        Some(Version::new(1, 31, 0))
+    )]
+    #[case(
+        r#"Client Version: version.Info{Major:"1", Minor:"24", GitVersion:"v1.24.3", GitCommit:"aef86a93"}"#,
+        Some(Version::new(1, 24, 3))
+    )]
+    #[case("v3.16.0-rc.1+g1a500d5", Some(Version::parse("3.16.0-rc.1").unwrap()))]
+    #[case("unknown", None)]
+    fn version_of_tool(#[case] output: &str, #[case] expected: Option<Version>) {
+        assert_eq!(parse_version(output), expected);
+    }
+}
@@ -0,0 +1,79 @@
+use serde_json::Value;
+use yaml_rust2::{Yaml, YamlEmitter, yaml::Hash};
+
+use crate::{Error, Result};
+
+/// Converts data of any other format into a YAML document, as helm only understands YAML
+pub(crate) fn to_yaml(value: &Value) -> Result<String> {
+    let mut output = String::new();
+    YamlEmitter::new(&mut output)
+        .dump(&convert(value))
+        .map_err(|err| Error::YamlError(format!("{err:?}")))?;
+
+    // The emitter always starts with a document marker, which is not needed for single documents
+    let output = output
+        .strip_prefix("---\n")
+        .or_else(|| output.strip_prefix("---"))
+        .unwrap_or(&output);
+
+    Ok(format!("{}\n", output.trim_start()))
+}
+
+fn convert(value: &Value) -> Yaml {
+    match value {
+        Value::Null => Yaml::Null,
+        Value::Bool(b) => Yaml::Boolean(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Yaml::Integer(i),
+            None => Yaml::Real(n.to_string()),
+        },
+        Value::String(s) => Yaml::String(s.clone()),
+        Value::Array(values) => Yaml::Array(values.iter().map(convert).collect()),
+        Value::Object(map) => {
+            let mut hash = Hash::new();
+            for (key, value) in map {
+                hash.insert(Yaml::String(key.clone()), convert(value));
+            }
+            Yaml::Hash(hash)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use yaml_rust2::YamlLoader;
+
+    use super::to_yaml;
+
+    type TestResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+    #[test]
+    fn convert_nested_values() -> TestResult {
+        // given
+        let value = json!({
+            "image": { "tag": "1.0", "pullPolicy": "Always" },
+            "replicas": 2,
+            "ratio": 0.5,
+            "enabled": true,
+            "args": ["--verbose", "true"],
+            "empty": null,
+        });
+
+        // when
+        let result = to_yaml(&value)?;
+
+        // then
+        assert!(!result.starts_with("---"));
+        let yaml = &YamlLoader::load_from_str(&result)?[0];
+        assert_eq!(yaml["image"]["tag"].as_str(), Some("1.0"));
+        assert_eq!(yaml["image"]["pullPolicy"].as_str(), Some("Always"));
+        assert_eq!(yaml["replicas"].as_i64(), Some(2));
+        assert_eq!(yaml["ratio"].as_f64(), Some(0.5));
+        assert_eq!(yaml["enabled"].as_bool(), Some(true));
+        assert_eq!(yaml["args"][1].as_str(), Some("true"));
+        assert!(yaml["empty"].is_null());
+
+        Ok(())
+    }
+}